@@ -0,0 +1,301 @@
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Match {
+    Exact,
+    IgnoringCase,
+    IgnoringDiacritics,
+    Typo(usize),
+    Wrong,
+}
+
+impl Match {
+    pub fn is_correct(&self) -> bool {
+        *self != Match::Wrong
+    }
+
+    pub fn desc(&self) -> String {
+        match self {
+            Match::Exact => "Correct!".to_string(),
+            Match::IgnoringCase => "Correct, but mind the letter case".to_string(),
+            Match::IgnoringDiacritics => "Correct, but mind the diacritics".to_string(),
+            Match::Typo(1) => "Almost correct: 1 typo".to_string(),
+            Match::Typo(typos) => format!("Almost correct: {} typos", typos),
+            Match::Wrong => "Wrong".to_string(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DiffChar {
+    Ok(char),
+    Wrong(char),
+    Missing(char),
+}
+
+// The answer may list alternatives separated by ',' or ';' and may contain
+// remarks in parentheses, e.g. "zestawienie (jako kontrast)"
+pub fn check(expected: &str, given: &str) -> Match {
+    accepted_answers(expected)
+        .iter()
+        .map(|answer| check_single(answer, given))
+        .min_by_key(|m| match m {
+            Match::Exact => 0,
+            Match::IgnoringCase => 1,
+            Match::IgnoringDiacritics => 2,
+            Match::Typo(typos) => 2 + typos,
+            Match::Wrong => usize::MAX,
+        })
+        .unwrap_or(Match::Wrong)
+}
+
+pub fn diff(expected: &str, given: &str) -> Vec<DiffChar> {
+    let given: Vec<char> = squeeze(given).chars().collect();
+    let expected: Vec<char> = accepted_answers(expected)
+        .into_iter()
+        .min_by_key(|answer| distance(&fold(answer), &fold_chars(&given)))
+        .unwrap_or_default()
+        .chars()
+        .collect();
+
+    let table = distance_table(&fold_chars(&expected), &fold_chars(&given));
+    let (mut i, mut j) = (expected.len(), given.len());
+    let mut result = vec![];
+    while i > 0 || j > 0 {
+        if i > 0 && j > 0 && fold_char(expected[i - 1]) == fold_char(given[j - 1]) {
+            result.push(DiffChar::Ok(given[j - 1]));
+            i -= 1;
+            j -= 1;
+        } else if i > 0 && j > 0 && table[i][j] == table[i - 1][j - 1] + 1 {
+            result.push(DiffChar::Wrong(given[j - 1]));
+            i -= 1;
+            j -= 1;
+        } else if j > 0 && (i == 0 || table[i][j] == table[i][j - 1] + 1) {
+            result.push(DiffChar::Wrong(given[j - 1]));
+            j -= 1;
+        } else {
+            result.push(DiffChar::Missing(expected[i - 1]));
+            i -= 1;
+        }
+    }
+    result.reverse();
+    result
+}
+
+fn check_single(expected: &str, given: &str) -> Match {
+    let given = squeeze(given);
+    let given = given.as_str();
+    if expected == given {
+        Match::Exact
+    } else if expected.to_lowercase() == given.to_lowercase() {
+        Match::IgnoringCase
+    } else {
+        let expected = fold(expected);
+        let given = fold(given);
+        if expected == given {
+            return Match::IgnoringDiacritics;
+        }
+        let typos = distance(&expected, &given);
+        if typos <= expected.len() / 4 {
+            Match::Typo(typos)
+        } else {
+            Match::Wrong
+        }
+    }
+}
+
+// Case and diacritic insensitive substring search
+pub fn contains(text: &str, query: &str) -> bool {
+    let query = fold(query.trim());
+    if query.is_empty() {
+        return false;
+    }
+    fold(text)
+        .windows(query.len())
+        .any(|window| window == query.as_slice())
+}
+
+fn accepted_answers(expected: &str) -> Vec<String> {
+    let mut without_remarks = String::new();
+    let mut depth = 0;
+    expected.chars().for_each(|c| match c {
+        '(' => depth += 1,
+        ')' => depth -= 1,
+        c if depth <= 0 => without_remarks.push(c),
+        _ => {}
+    });
+
+    // the translation as written is always accepted, also with its remarks and commas
+    let mut answers = vec![squeeze(expected)];
+    without_remarks
+        .split([',', ';'])
+        .map(squeeze)
+        .filter(|answer| !answer.is_empty())
+        .for_each(|answer| {
+            if !answers.contains(&answer) {
+                answers.push(answer);
+            }
+        });
+    answers
+}
+
+fn squeeze(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn fold_char(c: char) -> char {
+    let c = c.to_lowercase().next().unwrap_or(c);
+    match c {
+        // letters with a stroke have no decomposition
+        'ł' => 'l',
+        'ø' => 'o',
+        'đ' => 'd',
+        'ħ' => 'h',
+        'ı' => 'i',
+        c => c.nfd().find(|c| !is_combining_mark(*c)).unwrap_or(c),
+    }
+}
+
+fn fold(text: &str) -> Vec<char> {
+    text.chars().map(fold_char).collect()
+}
+
+fn fold_chars(text: &[char]) -> Vec<char> {
+    text.iter().copied().map(fold_char).collect()
+}
+
+fn distance(a: &[char], b: &[char]) -> usize {
+    distance_table(a, b)[a.len()][b.len()]
+}
+
+fn distance_table(a: &[char], b: &[char]) -> Vec<Vec<usize>> {
+    let mut table = vec![vec![0; b.len() + 1]; a.len() + 1];
+    (0..=a.len()).for_each(|i| table[i][0] = i);
+    (0..=b.len()).for_each(|j| table[0][j] = j);
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let substitution = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            table[i][j] = (table[i - 1][j] + 1)
+                .min(table[i][j - 1] + 1)
+                .min(table[i - 1][j - 1] + substitution);
+        }
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_contains_folded_query() {
+        assert!(contains("Niedźwiedź brunatny", "niedzwiedz"));
+        assert!(contains("a café", "CAFÉ"));
+        assert!(!contains("kot", "kota"));
+        assert!(!contains("kot", " "));
+    }
+
+    #[test]
+    fn exact_match() {
+        assert_eq!(Match::Exact, check("kot", "kot"));
+    }
+
+    #[test]
+    fn surrounding_whitespace_is_ignored() {
+        assert_eq!(Match::Exact, check("kot", "  kot "));
+    }
+
+    #[test]
+    fn case_insensitive_match() {
+        assert_eq!(Match::IgnoringCase, check("Kot", "kot"));
+    }
+
+    #[test]
+    fn diacritic_insensitive_match() {
+        assert_eq!(Match::IgnoringDiacritics, check("żółw", "zolw"));
+        assert_eq!(Match::IgnoringDiacritics, check("café", "Cafe"));
+    }
+
+    #[test]
+    fn small_typo_is_tolerated() {
+        assert_eq!(Match::Typo(1), check("niedźwiedź", "niedzwidz"));
+    }
+
+    #[test]
+    fn short_words_must_be_exact() {
+        assert_eq!(Match::Wrong, check("kot", "kit"));
+    }
+
+    #[test]
+    fn wrong_answer() {
+        assert_eq!(Match::Wrong, check("pies", "kot"));
+        assert!(!check("pies", "").is_correct());
+    }
+
+    #[test]
+    fn any_alternative_is_accepted() {
+        assert_eq!(Match::Exact, check("lion, big cat", "big cat"));
+    }
+
+    #[test]
+    fn remarks_in_parentheses_are_optional() {
+        assert_eq!(
+            Match::Exact,
+            check("zestawienie (jako kontrast)", "zestawienie")
+        );
+    }
+
+    #[test]
+    fn translation_as_written_is_accepted() {
+        assert_eq!(
+            Match::Exact,
+            check("zestawienie (jako kontrast)", "zestawienie (jako kontrast)")
+        );
+        assert_eq!(
+            Match::Exact,
+            check("in a moment, soon", "in a moment, soon")
+        );
+    }
+
+    #[test]
+    fn inner_whitespace_is_collapsed() {
+        assert_eq!(Match::Exact, check("big cat", "big   cat"));
+        assert_eq!(
+            Match::Exact,
+            check(
+                "zestawienie (jako kontrast)",
+                " zestawienie  (jako kontrast)"
+            )
+        );
+    }
+
+    #[test]
+    fn diff_marks_wrong_and_missing_chars() {
+        assert_eq!(
+            vec![
+                DiffChar::Ok('k'),
+                DiffChar::Wrong('u'),
+                DiffChar::Ok('t'),
+                DiffChar::Missing('y'),
+            ],
+            diff("koty", "kut")
+        );
+    }
+
+    #[test]
+    fn diff_marks_extra_chars() {
+        assert_eq!(
+            vec![DiffChar::Ok('k'), DiffChar::Wrong('x'), DiffChar::Ok('o')],
+            diff("ko", "kxo")
+        );
+    }
+
+    #[test]
+    fn diff_ignores_case_and_diacritics() {
+        assert_eq!(
+            vec![DiffChar::Ok('Z'), DiffChar::Ok('o'), DiffChar::Ok('l')],
+            diff("żół", "Zol")
+        );
+    }
+}
@@ -0,0 +1,367 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use std::rc::Rc;
+
+use cursive::event::Key;
+use cursive::traits::*;
+use cursive::views::{
+    Dialog, LinearLayout, NamedView, OnEventView, ScrollView, SelectView, TextView,
+};
+use cursive::Cursive;
+
+use crate::card::card_data::CardSet;
+use crate::card::card_logic;
+use crate::shortcuts::Shortcut;
+
+pub const BROWSER_DIALOG: &str = "browser_dialog";
+const BROWSER_HEADER: &str = "browser_header";
+const BROWSER_TABLE: &str = "browser_table";
+const BROWSER_SCROLL: &str = "browser_scroll";
+
+const NR_WIDTH: usize = 5;
+const WORD_WIDTH: usize = 20;
+const TRANSLATED_WIDTH: usize = 20;
+const TYPE_WIDTH: usize = 8;
+
+type Table = ScrollView<OnEventView<NamedView<SelectView<usize>>>>;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum SortBy {
+    Deck,
+    Word,
+    Translation,
+    Type,
+    Mark,
+    Stats,
+}
+
+impl SortBy {
+    fn next(self) -> Self {
+        match self {
+            SortBy::Deck => SortBy::Word,
+            SortBy::Word => SortBy::Translation,
+            SortBy::Translation => SortBy::Type,
+            SortBy::Type => SortBy::Mark,
+            SortBy::Mark => SortBy::Stats,
+            SortBy::Stats => SortBy::Deck,
+        }
+    }
+}
+
+struct Row {
+    word: String,
+    translated: String,
+    card_type: String,
+    marked: bool,
+    stats: String,
+}
+
+// Rows are indexed like the cards of the card set, the table keeps the indices
+struct Browser {
+    rows: Vec<Row>,
+    sort_by: SortBy,
+    descending: bool,
+    selected: BTreeSet<usize>,
+}
+
+impl Browser {
+    fn new(card_set: &CardSet) -> Self {
+        let rows = card_set
+            .cards()
+            .iter()
+            .enumerate()
+            .map(|(idx, card)| Row {
+                word: card.word.clone(),
+                translated: card.translated.clone(),
+                card_type: card.card_type.clone().unwrap_or_default(),
+                marked: card.to_be_repeated,
+                stats: card_set.card_stats(idx),
+            })
+            .collect();
+        Self {
+            rows,
+            sort_by: SortBy::Deck,
+            descending: false,
+            selected: BTreeSet::new(),
+        }
+    }
+
+    fn compare(&self, a: usize, b: usize) -> Ordering {
+        let (row_a, row_b) = (&self.rows[a], &self.rows[b]);
+        let ordering = match self.sort_by {
+            SortBy::Deck => Ordering::Equal,
+            SortBy::Word => compare_text(&row_a.word, &row_b.word),
+            SortBy::Translation => compare_text(&row_a.translated, &row_b.translated),
+            SortBy::Type => compare_text(&row_a.card_type, &row_b.card_type),
+            SortBy::Mark => row_b.marked.cmp(&row_a.marked),
+            SortBy::Stats => row_a.stats.cmp(&row_b.stats),
+        }
+        .then(a.cmp(&b));
+        if self.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+
+    fn title(&self) -> String {
+        format!(
+            "Cards: {} | marked: {} | selected: {}",
+            self.rows.len(),
+            self.rows.iter().filter(|row| row.marked).count(),
+            self.selected.len()
+        )
+    }
+
+    fn header(&self) -> String {
+        let column = |sort_by: SortBy, name: &str| {
+            if sort_by != self.sort_by {
+                name.to_string()
+            } else if self.descending {
+                format!("{} v", name)
+            } else {
+                format!("{} ^", name)
+            }
+        };
+        format!(
+            "{:<3}{:>nr$} {} {} {} {}",
+            column(SortBy::Mark, "R"),
+            column(SortBy::Deck, "#"),
+            cell(&column(SortBy::Word, "Word"), WORD_WIDTH),
+            cell(
+                &column(SortBy::Translation, "Translation"),
+                TRANSLATED_WIDTH
+            ),
+            cell(&column(SortBy::Type, "Type"), TYPE_WIDTH),
+            column(SortBy::Stats, "Stats"),
+            nr = NR_WIDTH
+        )
+    }
+
+    fn label(&self, idx: usize) -> String {
+        let row = &self.rows[idx];
+        format!(
+            "{}{} {:>nr$} {} {} {} {}",
+            if self.selected.contains(&idx) {
+                '>'
+            } else {
+                ' '
+            },
+            if row.marked { '*' } else { ' ' },
+            idx + 1,
+            cell(&row.word, WORD_WIDTH),
+            cell(&row.translated, TRANSLATED_WIDTH),
+            cell(&row.card_type, TYPE_WIDTH),
+            row.stats,
+            nr = NR_WIDTH
+        )
+    }
+
+    // Bulk actions apply to the selected cards, or to the focused one when none is selected
+    fn targets(&self, focused: Option<usize>) -> Vec<usize> {
+        if self.selected.is_empty() {
+            focused.into_iter().collect()
+        } else {
+            self.selected.iter().copied().collect()
+        }
+    }
+}
+
+fn compare_text(a: &str, b: &str) -> Ordering {
+    a.to_lowercase().cmp(&b.to_lowercase())
+}
+
+fn cell(text: &str, width: usize) -> String {
+    if text.chars().count() > width {
+        let truncated: String = text.chars().take(width - 1).collect();
+        format!("{}~", truncated)
+    } else {
+        format!("{:width$}", text, width = width)
+    }
+}
+
+pub fn show_browser(siv: &mut Cursive, card_set: &CardSet) {
+    let browser = Rc::new(RefCell::new(Browser::new(card_set)));
+    let keymap = card_logic::keymap(siv);
+
+    let mut table = SelectView::<usize>::new().on_submit(|s, idx: &usize| {
+        s.pop_layer();
+        card_logic::show_card(s, *idx);
+    });
+    {
+        let browser = browser.borrow();
+        table.add_all((0..browser.rows.len()).map(|idx| (browser.label(idx), idx)));
+    }
+    if let Some(current) = card_set.current_idx() {
+        table.set_selection(current);
+    }
+
+    let mut table = OnEventView::new(table.with_name(BROWSER_TABLE))
+        .on_event(' ', with_browser(&browser, toggle_selection))
+        .on_event(
+            's',
+            with_browser(&browser, |s, browser| {
+                browser.sort_by = browser.sort_by.next();
+                browser.descending = false;
+                sort(s, browser);
+            }),
+        )
+        .on_event(
+            'S',
+            with_browser(&browser, |s, browser| {
+                browser.descending = !browser.descending;
+                sort(s, browser);
+            }),
+        )
+        .on_event(Key::Esc, |s| {
+            s.pop_layer();
+        });
+    keymap
+        .events(Shortcut::MarkToRepeat)
+        .iter()
+        .for_each(|event| table.set_on_event(event.clone(), with_browser(&browser, toggle_marks)));
+
+    let mark_hint = keymap
+        .hint(Shortcut::MarkToRepeat)
+        .map(|key| format!(" | {}:mark", key))
+        .unwrap_or_default();
+    let hints = format!(
+        "| enter:review | space:select{} | s:sort | S:reverse | esc:close |",
+        mark_hint
+    );
+
+    let browser = browser.borrow();
+    let layout = LinearLayout::vertical()
+        .child(TextView::new(browser.header()).with_name(BROWSER_HEADER))
+        .child(table.scrollable().with_name(BROWSER_SCROLL).full_height())
+        .child(TextView::new(hints));
+    siv.add_layer(
+        Dialog::around(layout)
+            .title(browser.title())
+            .with_name(BROWSER_DIALOG)
+            .full_screen(),
+    );
+}
+
+fn with_browser<F>(browser: &Rc<RefCell<Browser>>, cb: F) -> impl Fn(&mut Cursive)
+where
+    F: Fn(&mut Cursive, &mut Browser),
+{
+    let browser = browser.clone();
+    move |s| cb(s, &mut browser.borrow_mut())
+}
+
+fn toggle_selection(siv: &mut Cursive, browser: &mut Browser) {
+    if let Some(mut table) = siv.find_name::<SelectView<usize>>(BROWSER_TABLE) {
+        if let Some(idx) = table.selection().map(|idx| *idx) {
+            if !browser.selected.remove(&idx) {
+                browser.selected.insert(idx);
+            }
+            table.select_down(1);
+        }
+    }
+    refresh(siv, browser);
+}
+
+fn toggle_marks(siv: &mut Cursive, browser: &mut Browser) {
+    let focused = siv
+        .find_name::<SelectView<usize>>(BROWSER_TABLE)
+        .and_then(|table| table.selection().map(|idx| *idx));
+    let targets = browser.targets(focused);
+    if targets.is_empty() {
+        return;
+    }
+    let marked = card_logic::toggle_repeat_cards(siv, &targets);
+    targets
+        .iter()
+        .for_each(|idx| browser.rows[*idx].marked = marked);
+    browser.selected.clear();
+    refresh(siv, browser);
+}
+
+// The focus stays on the same card
+fn sort(siv: &mut Cursive, browser: &mut Browser) {
+    if let Some(mut table) = siv.find_name::<SelectView<usize>>(BROWSER_TABLE) {
+        let focused = table.selection().map(|idx| *idx);
+        table.sort_by(|a, b| browser.compare(*a, *b));
+        let position =
+            focused.and_then(|focused| table.iter().position(|(_, idx)| *idx == focused));
+        if let Some(position) = position {
+            table.set_selection(position);
+        }
+    }
+    refresh(siv, browser);
+}
+
+// The table changes in callbacks, after the scroll view followed the focus
+fn refresh(siv: &mut Cursive, browser: &Browser) {
+    if let Some(mut table) = siv.find_name::<SelectView<usize>>(BROWSER_TABLE) {
+        table
+            .iter_mut()
+            .for_each(|(label, idx)| *label = browser.label(*idx).into());
+    }
+    if let Some(mut scroll) = siv.find_name::<Table>(BROWSER_SCROLL) {
+        scroll.scroll_to_important_area();
+    }
+    if let Some(mut header) = siv.find_name::<TextView>(BROWSER_HEADER) {
+        header.set_content(browser.header());
+    }
+    if let Some(mut dialog) = siv.find_name::<Dialog>(BROWSER_DIALOG) {
+        dialog.set_title(browser.title());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::card_data::CardData;
+
+    fn browser(words: &[(&str, &str)]) -> Browser {
+        let cards = words
+            .iter()
+            .map(|(word, card_type)| CardData {
+                word: word.to_string(),
+                translated: word.to_uppercase(),
+                card_type: Some(card_type.to_string()),
+                ..CardData::default()
+            })
+            .collect();
+        Browser::new(&CardSet::new("browser", cards))
+    }
+
+    fn sorted(browser: &Browser) -> Vec<usize> {
+        let mut idxs: Vec<usize> = (0..browser.rows.len()).collect();
+        idxs.sort_by(|a, b| browser.compare(*a, *b));
+        idxs
+    }
+
+    #[test]
+    fn rows_are_sorted_by_column() {
+        let mut browser = browser(&[("kot", "noun"), ("Biec", "verb"), ("arbuz", "noun")]);
+        assert_eq!(vec![0, 1, 2], sorted(&browser));
+
+        browser.sort_by = SortBy::Word;
+        assert_eq!(vec![2, 1, 0], sorted(&browser));
+
+        browser.sort_by = SortBy::Type;
+        browser.descending = true;
+        assert_eq!(vec![1, 2, 0], sorted(&browser));
+    }
+
+    #[test]
+    fn bulk_actions_prefer_selection() {
+        let mut browser = browser(&[("kot", "noun"), ("pies", "noun"), ("mysz", "noun")]);
+        assert_eq!(vec![1], browser.targets(Some(1)));
+
+        browser.selected.insert(2);
+        browser.selected.insert(0);
+        assert_eq!(vec![0, 2], browser.targets(Some(1)));
+    }
+
+    #[test]
+    fn long_cells_are_truncated() {
+        assert_eq!("kot  ", cell("kot", 5));
+        assert_eq!("niedź~", cell("niedźwiedź", 6));
+    }
+}
@@ -1,18 +1,243 @@
-#[derive(serde::Serialize, serde::Deserialize, Debug, PartialOrd, PartialEq)]
+use std::borrow::Cow;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use log::info;
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+
+use crate::answer::{self, Match};
+use crate::card::filter::Filter;
+use crate::error::Error;
+use crate::scheduler::{Linear, Scheduler};
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default, PartialOrd, PartialEq)]
 pub struct CardData {
     pub word: String,
     pub translated: String,
     pub explanation: String,
     pub pronunciation: String,
     pub sentence: String,
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub card_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sentence_gap: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gap_term: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
     #[serde(skip)]
     pub to_be_repeated: bool,
+    #[serde(skip)]
+    pub seen: bool,
+    #[serde(skip)]
+    pub lapsed: bool,
+    #[serde(skip)]
+    pub revealed: bool,
+    // Deck file the card was read from
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
+    // Place of the card in the file it is saved to
+    #[serde(skip)]
+    pub position: Option<usize>,
+}
+
+impl CardData {
+    pub fn id(&self) -> String {
+        let hash = fnv1a(
+            self.word
+                .bytes()
+                .chain(std::iter::once(0x1f))
+                .chain(self.translated.bytes()),
+        );
+        format!("{:016x}", hash)
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags
+            .iter()
+            .any(|own| own.trim().eq_ignore_ascii_case(tag.trim()))
+    }
+
+    pub fn matches(&self, query: &str) -> bool {
+        [
+            &self.word,
+            &self.translated,
+            &self.explanation,
+            &self.sentence,
+        ]
+        .iter()
+        .any(|field| answer::contains(field, query))
+    }
+
+    pub fn cloze(&self) -> Option<Cloze> {
+        if let Some(sentence_gap) = &self.sentence_gap {
+            let term = self.gap_term.as_ref().unwrap_or(&self.word);
+            let sentence = if self.sentence.is_empty() {
+                fill_gap(sentence_gap, term)
+            } else {
+                self.sentence.clone()
+            };
+            return Some(Cloze {
+                sentence_gap: sentence_gap.clone(),
+                sentence,
+                term: term.clone(),
+            });
+        }
+
+        let (start, end) = find_ignoring_case(&self.sentence, &self.word)?;
+        Some(Cloze {
+            sentence_gap: format!(
+                "{}{}{}",
+                &self.sentence[..start],
+                GAP,
+                &self.sentence[end..]
+            ),
+            sentence: self.sentence.clone(),
+            term: self.sentence[start..end].to_string(),
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Cloze {
+    pub sentence_gap: String,
+    pub sentence: String,
+    pub term: String,
+}
+
+const GAP: &str = "______";
+
+fn fill_gap(sentence_gap: &str, term: &str) -> String {
+    match sentence_gap.find('_') {
+        Some(start) => {
+            let end = sentence_gap[start..]
+                .find(|c| c != '_')
+                .map(|len| start + len)
+                .unwrap_or_else(|| sentence_gap.len());
+            format!("{}{}{}", &sentence_gap[..start], term, &sentence_gap[end..])
+        }
+        None => sentence_gap.to_string(),
+    }
+}
+
+fn find_ignoring_case(text: &str, term: &str) -> Option<(usize, usize)> {
+    if term.is_empty() {
+        return None;
+    }
+    text.char_indices().find_map(|(start, _)| {
+        let mut text_chars = text[start..].char_indices();
+        let matches = term.chars().all(|term_char| match text_chars.next() {
+            Some((_, c)) => c.to_lowercase().eq(term_char.to_lowercase()),
+            None => false,
+        });
+        if matches {
+            let end = text_chars
+                .next()
+                .map(|(offset, _)| start + offset)
+                .unwrap_or_else(|| text.len());
+            Some((start, end))
+        } else {
+            None
+        }
+    })
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Grade {
+    Again,
+    Hard,
+    Good,
+    Easy,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Flip,
+    Type,
+    Cloze,
+    Choice,
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "flip" => Ok(Mode::Flip),
+            "type" => Ok(Mode::Type),
+            "cloze" => Ok(Mode::Cloze),
+            "choice" => Ok(Mode::Choice),
+            _ => Err(format!("Unknown mode: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Choice<'a> {
+    pub text: &'a str,
+    pub correct: bool,
+    pub chosen: bool,
+}
+
+// Tags are written separated by spaces or commas, as in Anki
+pub fn split_tags(text: &str) -> Vec<String> {
+    text.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|tag| !tag.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+// FNV-1a, so hashes stay the same between runs and platforms
+pub fn fnv1a<I: IntoIterator<Item = u8>>(bytes: I) -> u64 {
+    bytes
+        .into_iter()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Visibility {
+    pub show_pronunciation: bool,
+    pub show_description: bool,
+    pub show_example: bool,
+    pub show_title: bool,
+    pub show_hints: bool,
+    pub zen_mode: bool,
+}
+
+impl Default for Visibility {
+    fn default() -> Self {
+        Self {
+            show_pronunciation: true,
+            show_description: false,
+            show_example: false,
+            show_title: true,
+            show_hints: true,
+            zen_mode: false,
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct CardSet {
     pub name: String,
     cards: Vec<CardData>,
+    round: Vec<usize>,
+    // The round before the filter narrowed it
+    full_round: Vec<usize>,
+    filter: Filter,
+    round_nr: usize,
+    round_finished: bool,
+    shuffle_rounds: bool,
+    answers: Vec<(usize, Grade)>,
+    mode: Mode,
+    scheduler: Box<dyn Scheduler>,
+    typed_answer: Option<(String, Match)>,
+    choices: Vec<usize>,
+    chosen: Option<usize>,
     pub current_card: usize,
     reversed: bool,
     show_pronunciation: bool,
@@ -23,13 +248,26 @@ pub struct CardSet {
     zen_mode: bool,
 }
 
-const EMPTY_SET: &'static str = "EMPTY SET";
+const EMPTY_SET: &str = "EMPTY SET";
+pub const CHOICES: usize = 4;
 
 impl CardSet {
     pub fn new(name: &str, cards: Vec<CardData>) -> Self {
-        Self {
+        let mut set = Self {
             name: name.to_string(),
+            round: (0..cards.len()).collect(),
+            full_round: (0..cards.len()).collect(),
+            filter: Filter::default(),
             cards,
+            round_nr: 1,
+            round_finished: false,
+            shuffle_rounds: false,
+            answers: vec![],
+            mode: Mode::Flip,
+            scheduler: Box::new(Linear),
+            typed_answer: None,
+            choices: vec![],
+            chosen: None,
             current_card: 0,
             reversed: false,
             show_pronunciation: true,
@@ -38,9 +276,141 @@ impl CardSet {
             show_title: true,
             show_hints: true,
             zen_mode: false,
+        };
+        set.enter_current_card();
+        set
+    }
+
+    pub fn shuffle_rounds(mut self, shuffle: bool) -> Self {
+        self.shuffle_rounds = shuffle;
+        self
+    }
+
+    pub fn scheduler(mut self, scheduler: Box<dyn Scheduler>) -> Self {
+        self.cards.iter_mut().for_each(|card| card.seen = false);
+        self.full_round = (0..self.cards.len())
+            .filter(|idx| scheduler.is_due(&self.cards[*idx]))
+            .collect();
+        self.round = self.filtered(&self.full_round);
+        self.scheduler = scheduler;
+        self.current_card = 0;
+        self.enter_current_card();
+        self
+    }
+
+    pub fn mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self.enter_current_card();
+        self
+    }
+
+    pub fn visibility(mut self, visibility: Visibility) -> Self {
+        self.show_pronunciation = visibility.show_pronunciation;
+        self.show_description = visibility.show_description;
+        self.show_example = visibility.show_example;
+        self.show_title = visibility.show_title;
+        self.show_hints = visibility.show_hints;
+        self.zen_mode = visibility.zen_mode;
+        self
+    }
+
+    pub fn mark_cards(mut self, ids: &[String]) -> Self {
+        self.cards
+            .iter_mut()
+            .filter(|card| ids.contains(&card.id()))
+            .for_each(|card| card.to_be_repeated = true);
+        self
+    }
+
+    // Later rounds hold only the cards marked in the round before; unknown cards are left out
+    pub fn resume_round(mut self, round_nr: usize, card_ids: &[String]) -> Self {
+        let cards = &self.cards;
+        let round: Vec<usize> = card_ids
+            .iter()
+            .filter_map(|id| cards.iter().position(|card| card.id() == *id))
+            .collect();
+        if round_nr <= 1 || round.is_empty() {
+            return self;
+        }
+        round.iter().for_each(|idx| self.cards[*idx].lapsed = true);
+        self.round_nr = round_nr;
+        self.full_round = round;
+        self.round = self.filtered(&self.full_round);
+        self.move_to(0);
+        self
+    }
+
+    // Only flipped cards are shown reversed again; typed answers and choices are not kept
+    pub fn resume_at(mut self, card_id: &str, reversed: bool) -> Self {
+        let cards = &self.cards;
+        if let Some(position) = self
+            .round
+            .iter()
+            .position(|idx| cards[*idx].id() == card_id)
+        {
+            self.cards.iter_mut().for_each(|card| card.seen = false);
+            self.move_to(position);
+            self.reversed = reversed && matches!(self.mode, Mode::Flip | Mode::Cloze);
+        }
+        self
+    }
+
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.set_filter(filter);
+        self
+    }
+
+    // Narrows the round to the matching cards without reloading; the current card
+    // stays when it matches, the round starts over otherwise
+    pub fn set_filter(&mut self, filter: Filter) {
+        let current = self.current_idx();
+        self.filter = filter;
+        self.round = self.filtered(&self.full_round);
+        self.round_finished = false;
+        match current.and_then(|idx| self.position_of(idx)) {
+            Some(position) => self.current_card = position,
+            None => {
+                self.leave_current_card();
+                self.current_card = 0;
+                self.enter_current_card();
+            }
+        }
+    }
+
+    pub fn get_filter(&self) -> &Filter {
+        &self.filter
+    }
+
+    fn filtered(&self, round: &[usize]) -> Vec<usize> {
+        round
+            .iter()
+            .copied()
+            .filter(|idx| self.filter.matches(&self.cards[*idx]))
+            .collect()
+    }
+
+    pub fn get_visibility(&self) -> Visibility {
+        Visibility {
+            show_pronunciation: self.show_pronunciation,
+            show_description: self.show_description,
+            show_example: self.show_example,
+            show_title: self.show_title,
+            show_hints: self.show_hints,
+            zen_mode: self.zen_mode,
         }
     }
 
+    pub fn cards(&self) -> &[CardData] {
+        &self.cards
+    }
+
+    // The given cards were saved to a file in this order
+    pub fn set_positions(&mut self, idxs: &[usize]) {
+        idxs.iter()
+            .enumerate()
+            .for_each(|(position, idx)| self.cards[*idx].position = Some(position));
+    }
+
     pub fn count_view_weight(&self, x: usize, desc_prefix: usize, example_prefix: usize) -> i32 {
         fn multirow_with_prefix(item: &Option<&str>, x: usize, prefix_len: usize) -> i32 {
             *item
@@ -63,7 +433,114 @@ impl CardSet {
     }
 
     pub fn cards_len(&self) -> usize {
-        self.cards.len()
+        self.round.len()
+    }
+
+    pub fn round_nr(&self) -> usize {
+        self.round_nr
+    }
+
+    pub fn cards_left(&self) -> usize {
+        self.round.len().saturating_sub(self.current_card + 1)
+    }
+
+    pub fn is_round_finished(&self) -> bool {
+        self.round_finished
+    }
+
+    pub fn cards_to_repeat(&self) -> usize {
+        self.round
+            .iter()
+            .filter_map(|idx| self.cards.get(*idx))
+            .filter(|card| card.to_be_repeated)
+            .count()
+    }
+
+    pub fn start_next_round(&mut self) -> bool {
+        let mut next_round: Vec<usize> = self
+            .round
+            .iter()
+            .copied()
+            .filter(|idx| self.cards[*idx].to_be_repeated)
+            .collect();
+        if next_round.is_empty() {
+            return false;
+        }
+
+        if self.shuffle_rounds {
+            info!("shuffling round {}", self.round_nr + 1);
+            next_round.shuffle(&mut thread_rng());
+        }
+        next_round.iter().for_each(|idx| {
+            let card = &mut self.cards[*idx];
+            card.to_be_repeated = false;
+            card.lapsed = true;
+        });
+
+        self.leave_current_card();
+        self.full_round = next_round.clone();
+        self.round = next_round;
+        self.round_nr += 1;
+        self.round_finished = false;
+        self.current_card = 0;
+        self.reversed = false;
+        self.enter_current_card();
+        true
+    }
+
+    pub fn round_cards(&self) -> impl Iterator<Item = &CardData> {
+        self.full_round.iter().map(move |idx| &self.cards[*idx])
+    }
+
+    pub fn marked_cards(&self) -> impl Iterator<Item = &CardData> {
+        self.cards.iter().filter(|card| card.to_be_repeated)
+    }
+
+    pub fn answers(&self) -> &[(usize, Grade)] {
+        &self.answers
+    }
+
+    // Cards only passed on the way to another one were not reviewed
+    fn reviewed(&self) -> Vec<(usize, Grade)> {
+        self.cards
+            .iter()
+            .enumerate()
+            .filter(|(idx, card)| {
+                card.revealed || self.answers.iter().any(|(answered, _)| answered == idx)
+            })
+            .map(|(idx, card)| {
+                let first_answer = self
+                    .answers
+                    .iter()
+                    .find(|(answered, _)| *answered == idx)
+                    .map(|(_, grade)| *grade);
+                let grade = match first_answer {
+                    Some(grade) => grade,
+                    None if card.lapsed || card.to_be_repeated => Grade::Again,
+                    None => Grade::Good,
+                };
+                (idx, grade)
+            })
+            .collect()
+    }
+
+    pub fn save_progress(&mut self) -> Result<(), Error> {
+        for (idx, grade) in self.reviewed() {
+            self.scheduler.record(&self.cards[idx], grade);
+        }
+        self.scheduler.save()
+    }
+
+    pub fn grade_current_card(&mut self, grade: Grade) {
+        if !self.reversed {
+            return;
+        }
+        if let Some(idx) = self.round.get(self.current_card).copied() {
+            self.answers.push((idx, grade));
+            self.cards[idx].to_be_repeated = grade == Grade::Again;
+            self.reversed = false;
+            self.next_card();
+        }
     }
 
     pub fn toggle_show_pronunciation(&mut self) {
@@ -74,7 +551,7 @@ impl CardSet {
     }
 
     pub fn toggle_repeat_card(&mut self) {
-        if let Some(card) = self.cards.get_mut(self.current_card) {
+        if let Some(card) = self.get_current_card_mut() {
             card.to_be_repeated = !card.to_be_repeated;
         }
     }
@@ -118,27 +595,48 @@ impl CardSet {
         self.zen_mode = !self.zen_mode
     }
 
-    pub fn get_main_text(&self) -> &str {
+    pub fn get_main_text(&self) -> Cow<'_, str> {
         match self.get_current_card() {
-            Some(card) => {
-                if self.reversed {
-                    &card.translated
-                } else {
-                    &card.word
-                }
-            }
-            None => EMPTY_SET,
+            Some(card) => match self.get_cloze() {
+                Some(cloze) if self.reversed => Cow::Owned(cloze.sentence),
+                Some(cloze) => Cow::Owned(cloze.sentence_gap),
+                None if self.reversed => Cow::Borrowed(&card.translated),
+                None => Cow::Borrowed(&card.word),
+            },
+            None => Cow::Borrowed(EMPTY_SET),
         }
     }
 
-    pub fn get_title(&self) -> Option<&str> {
-        if self.show_title && !self.zen_mode {
-            Some(&self.name)
+    pub fn get_revealed_term(&self) -> Option<String> {
+        if self.reversed {
+            self.get_cloze().map(|cloze| cloze.term)
         } else {
             None
         }
     }
 
+    fn get_cloze(&self) -> Option<Cloze> {
+        if self.mode == Mode::Cloze {
+            self.get_current_card()?.cloze()
+        } else {
+            None
+        }
+    }
+
+    // Cards of merged decks show the deck they come from
+    pub fn get_title(&self) -> Option<Cow<'_, str>> {
+        if !self.show_title || self.zen_mode {
+            return None;
+        }
+        match self
+            .get_current_card()
+            .and_then(|card| card.source.as_ref())
+        {
+            Some(source) => Some(source.to_string_lossy()),
+            None => Some(Cow::from(self.name.as_str())),
+        }
+    }
+
     pub fn show_hints(&self) -> bool {
         self.show_hints && !self.zen_mode
     }
@@ -163,7 +661,7 @@ impl CardSet {
 
     pub fn get_example(&self) -> Option<&str> {
         let card = self.get_current_card()?;
-        if self.show_example && !self.zen_mode && !self.reversed {
+        if self.show_example && !self.zen_mode && !self.reversed && self.get_cloze().is_none() {
             Some(&card.sentence)
         } else {
             None
@@ -176,32 +674,319 @@ impl CardSet {
             .unwrap_or(false)
     }
 
-    fn get_current_card(&self) -> Option<&CardData> {
-        self.cards.get(self.current_card)
+    pub fn get_current_card(&self) -> Option<&CardData> {
+        self.round
+            .get(self.current_card)
+            .and_then(|idx| self.cards.get(*idx))
+    }
+
+    fn get_current_card_mut(&mut self) -> Option<&mut CardData> {
+        let cards = &mut self.cards;
+        self.round
+            .get(self.current_card)
+            .and_then(move |idx| cards.get_mut(*idx))
+    }
+
+    pub fn get_mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn awaits_typed_answer(&self) -> bool {
+        self.mode == Mode::Type && self.typed_answer.is_none() && self.get_current_card().is_some()
+    }
+
+    pub fn get_typed_answer(&self) -> Option<(&str, Match)> {
+        self.typed_answer
+            .as_ref()
+            .map(|(answer, result)| (answer.as_str(), *result))
+    }
+
+    pub fn get_expected_answer(&self) -> Option<&str> {
+        self.get_current_card().map(|card| card.translated.as_str())
+    }
+
+    pub fn check_typed_answer(&mut self, answer: &str) {
+        if !self.awaits_typed_answer() {
+            return;
+        }
+        if let Some(card) = self.get_current_card_mut() {
+            let result = answer::check(&card.translated, answer);
+            card.to_be_repeated = !result.is_correct();
+            self.typed_answer = Some((answer.to_string(), result));
+            self.reveal();
+        }
+    }
+
+    fn leave_current_card(&mut self) {
+        let chosen = self.chosen.take();
+        if self.typed_answer.take().is_some() || chosen.is_some() {
+            self.reversed = false;
+        }
+        self.choices.clear();
+    }
+
+    pub fn is_reversed(&self) -> bool {
+        self.reversed
     }
 
     pub fn reverse_current_card(&mut self) {
-        self.reversed = !self.reversed
+        if self.reversed {
+            self.reversed = false;
+        } else {
+            self.reveal();
+        }
+    }
+
+    fn reveal(&mut self) {
+        self.reversed = true;
+        if let Some(card) = self.get_current_card_mut() {
+            card.revealed = true;
+        }
     }
 
     pub fn next_card(&mut self) {
-        self.current_card = if self.current_card + 1 < self.cards.len() {
-            self.current_card + 1
+        if self.current_card + 1 < self.round.len() {
+            self.move_to(self.current_card + 1);
         } else {
-            self.current_card
+            self.round_finished = !self.round.is_empty();
+        }
+    }
+
+    fn move_to(&mut self, position: usize) {
+        self.leave_current_card();
+        self.current_card = position;
+        self.enter_current_card();
+    }
+
+    fn enter_current_card(&mut self) {
+        let reversed = self.reversed;
+        if let Some(card) = self.get_current_card_mut() {
+            card.seen = true;
+            // the translation is shown right away
+            card.revealed |= reversed;
+        }
+        if self.mode == Mode::Choice {
+            self.choices = self.draw_choices();
+        }
+    }
+
+    fn draw_choices(&self) -> Vec<usize> {
+        let current = match self.round.get(self.current_card) {
+            Some(idx) => *idx,
+            None => return vec![],
         };
+        let card = &self.cards[current];
+
+        let mut candidates: Vec<usize> = vec![];
+        (0..self.cards.len()).for_each(|idx| {
+            let translated = &self.cards[idx].translated;
+            let duplicate = *translated == card.translated
+                || candidates
+                    .iter()
+                    .any(|other| self.cards[*other].translated == *translated);
+            if !duplicate {
+                candidates.push(idx);
+            }
+        });
+
+        let mut rng = thread_rng();
+        candidates.shuffle(&mut rng);
+        // distractors of the same type first, when type is known
+        candidates.sort_by_key(|idx| {
+            card.card_type.is_none() || self.cards[*idx].card_type != card.card_type
+        });
+
+        let mut choices: Vec<usize> = candidates.into_iter().take(CHOICES - 1).collect();
+        choices.push(current);
+        choices.shuffle(&mut rng);
+        choices
     }
 
-    pub fn prev_card(&mut self) {
-        self.current_card = if self.current_card > 0 {
-            self.current_card - 1
+    pub fn awaits_choice(&self) -> bool {
+        self.mode == Mode::Choice && self.chosen.is_none() && !self.choices.is_empty()
+    }
+
+    pub fn get_choices(&self) -> Vec<Choice<'_>> {
+        let current = self.round.get(self.current_card).copied();
+        self.choices
+            .iter()
+            .enumerate()
+            .map(|(nr, idx)| Choice {
+                text: &self.cards[*idx].translated,
+                correct: Some(*idx) == current,
+                chosen: self.chosen == Some(nr),
+            })
+            .collect()
+    }
+
+    pub fn choose(&mut self, nr: usize) {
+        if !self.awaits_choice() || nr >= self.choices.len() {
+            return;
+        }
+        let correct = self.round.get(self.current_card) == Some(&self.choices[nr]);
+        if let Some(card) = self.get_current_card_mut() {
+            card.to_be_repeated = !correct;
+        }
+        self.chosen = Some(nr);
+        self.reveal();
+    }
+
+    // Indexes of all cards matching `query`, also the ones outside of this round
+    pub fn search(&self, query: &str) -> Vec<usize> {
+        self.cards
+            .iter()
+            .enumerate()
+            .filter(|(_, card)| card.matches(query))
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    // Cycles through the matches of `query` in this round, wrapping around it
+    pub fn go_to_match(&mut self, query: &str, forward: bool) {
+        let mut matches: Vec<usize> = self
+            .search(query)
+            .into_iter()
+            .filter_map(|idx| self.position_of(idx))
+            .collect();
+        matches.sort_unstable();
+        let current = self.current_card;
+        let found = if forward {
+            matches
+                .iter()
+                .find(|position| **position > current)
+                .or(matches.first())
         } else {
-            self.current_card
+            matches
+                .iter()
+                .rev()
+                .find(|position| **position < current)
+                .or(matches.last())
         };
+        if let Some(position) = found.copied() {
+            self.go_to(position);
+        }
+    }
+
+    // Returns the position of the new card in the round
+    pub fn insert_card(&mut self, card: CardData, after_current: bool) -> usize {
+        self.cards.push(card);
+        let idx = self.cards.len() - 1;
+        let full_position = match self.current_idx() {
+            Some(current) if after_current => self
+                .full_round
+                .iter()
+                .position(|other| *other == current)
+                .map_or(self.full_round.len(), |position| position + 1),
+            _ => self.full_round.len(),
+        };
+        self.full_round.insert(full_position, idx);
+        if self.round.is_empty() {
+            self.round.push(idx);
+            self.current_card = 0;
+            self.enter_current_card();
+            return 0;
+        }
+        let position = if after_current {
+            self.current_card + 1
+        } else {
+            self.round.len()
+        };
+        self.round.insert(position, idx);
+        self.round_finished = false;
+        position
+    }
+
+    pub fn find_word(&self, word: &str) -> Option<&CardData> {
+        let word = word.trim().to_lowercase();
+        self.cards
+            .iter()
+            .find(|card| card.word.trim().to_lowercase() == word)
+    }
+
+    // Keeps the review state, place in the deck file and scheduling progress of
+    // the card, even when the edit changes its id; returns the card before the edit
+    pub fn update_current_card(&mut self, card: CardData) -> Option<CardData> {
+        let idx = self.current_idx()?;
+        let current = &mut self.cards[idx];
+        let edited = CardData {
+            to_be_repeated: current.to_be_repeated,
+            seen: current.seen,
+            lapsed: current.lapsed,
+            revealed: current.revealed,
+            source: current.source.clone(),
+            position: current.position,
+            ..card
+        };
+        let old = std::mem::replace(current, edited);
+        self.scheduler.rename_card(&old, &self.cards[idx]);
+        Some(old)
+    }
+
+    pub fn current_idx(&self) -> Option<usize> {
+        self.round.get(self.current_card).copied()
+    }
+
+    pub fn position_of(&self, idx: usize) -> Option<usize> {
+        self.round.iter().position(|card| *card == idx)
+    }
+
+    pub fn hidden_by_filter(&self, idx: usize) -> bool {
+        self.position_of(idx).is_none() && self.full_round.contains(&idx)
+    }
+
+    // Goes to the card, the filter is cleared when it hides the card; false when
+    // the card is not in this round at all
+    pub fn show_card(&mut self, idx: usize) -> bool {
+        if self.hidden_by_filter(idx) {
+            self.set_filter(Filter::default());
+        }
+        match self.position_of(idx) {
+            Some(position) => {
+                self.go_to(position);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn card_stats(&self, idx: usize) -> String {
+        self.scheduler.stats(&self.cards[idx])
+    }
+
+    // Marks all given cards, unless all of them are marked already; returns the new mark
+    pub fn toggle_repeat_cards(&mut self, idxs: &[usize]) -> bool {
+        let mark = !idxs.iter().all(|idx| self.cards[*idx].to_be_repeated);
+        idxs.iter()
+            .for_each(|idx| self.cards[*idx].to_be_repeated = mark);
+        mark
+    }
+
+    pub fn card_at(&self, position: usize) -> Option<&CardData> {
+        self.round.get(position).map(|idx| &self.cards[*idx])
+    }
+
+    // Positions past the end of the round go to the last card
+    pub fn go_to(&mut self, position: usize) {
+        if self.round.is_empty() {
+            return;
+        }
+        self.round_finished = false;
+        let position = position.min(self.round.len() - 1);
+        if position != self.current_card {
+            self.move_to(position);
+        }
+    }
+
+    pub fn prev_card(&mut self) {
+        self.round_finished = false;
+        if self.current_card > 0 {
+            self.move_to(self.current_card - 1);
+        }
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
     use super::*;
 
@@ -213,17 +998,318 @@ mod tests {
     }
 
     #[test]
-    fn dont_repeat_card_by_default() {
+    fn dont_repeat_card_by_default() {
+        let set = card_set("regular set");
+        assert!(!set.repeat_current_card())
+    }
+
+    #[test]
+    fn repeat_card_when_toggled() {
+        let mut  set = card_set("regular set");
+        set.toggle_repeat_card();
+
+        assert!(set.repeat_current_card())
+    }
+
+    #[test]
+    fn marked_cards_are_listed() {
+        let mut set = card_set("regular set");
+        set.next_card();
+        set.toggle_repeat_card();
+
+        let marked: Vec<&str> = set.marked_cards().map(|card| card.word.as_str()).collect();
+
+        assert_eq!(vec![gen_card_data(1).word.as_str()], marked);
+    }
+
+    #[test]
+    fn resume_at_known_card() {
+        let second = gen_card_data(1).id();
+
+        let set = card_set("regular set")
+            .mode(Mode::Type)
+            .resume_at(&second, true);
+
+        assert_eq!(1, set.current_card);
+        assert!(!set.is_reversed());
+        assert!(set.awaits_typed_answer());
+        assert_eq!(1, set.cards().iter().filter(|card| card.seen).count());
+    }
+
+    #[test]
+    fn resume_at_unknown_card_stays_at_first() {
+        let set = card_set("regular set").resume_at("0000000000000000", true);
+
+        assert_eq!(0, set.current_card);
+        assert!(!set.is_reversed());
+    }
+
+    #[test]
+    fn search_finds_all_cards() {
+        let mut cards = vec![
+            typed_card(0, "noun"),
+            typed_card(1, "verb"),
+            typed_card(2, "noun"),
+        ];
+        cards[1].sentence = "Żółw śpi".to_string();
+        cards[2].explanation = "zolwie jaja".to_string();
+        let set = CardSet::new("search", cards).filter(Filter {
+            types: vec!["noun".to_string()],
+            ..Filter::default()
+        });
+
+        assert_eq!(vec![1, 2], set.search("ZÓŁW"));
+        assert!(set.search("").is_empty());
+        assert!(set.hidden_by_filter(1));
+        assert!(!set.hidden_by_filter(2));
+    }
+
+    #[test]
+    fn card_hidden_by_filter_is_shown_without_it() {
+        let cards = vec![
+            typed_card(0, "noun"),
+            typed_card(1, "verb"),
+            typed_card(2, "noun"),
+        ];
+        let mut set = CardSet::new("search", cards).filter(Filter {
+            types: vec!["noun".to_string()],
+            ..Filter::default()
+        });
+
+        assert!(set.show_card(1));
+        assert_eq!(3, set.cards_len());
+        assert_eq!(Some(1), set.current_idx());
+        assert!(set.get_filter().is_empty());
+    }
+
+    #[test]
+    fn cards_hidden_by_filter_have_no_position() {
+        let cards = vec![
+            typed_card(0, "noun"),
+            typed_card(1, "verb"),
+            typed_card(2, "noun"),
+        ];
+        let mut set = CardSet::new("browse", cards).filter(Filter {
+            types: vec!["noun".to_string()],
+            ..Filter::default()
+        });
+
+        assert_eq!(None, set.position_of(1));
+        assert_eq!(Some(1), set.position_of(2));
+        set.go_to(5);
+        assert_eq!(Some(2), set.current_idx());
+    }
+
+    #[test]
+    fn card_outside_of_round_is_not_shown() {
+        let mut set = card_set("search");
+        set.toggle_repeat_card();
+        set.next_card();
+        set.next_card();
+        set.start_next_round();
+
+        assert!(!set.show_card(1));
+        assert_eq!(Some(0), set.current_idx());
+    }
+
+    #[test]
+    fn search_matches_wrap_around() {
+        let mut cards: Vec<CardData> = (0..4).map(gen_card_data).collect();
+        cards[0].sentence = "kot".to_string();
+        cards[2].sentence = "kot".to_string();
+        let mut set = CardSet::new("search", cards);
+
+        set.go_to_match("kot", true);
+        assert_eq!(2, set.current_card);
+        set.go_to_match("kot", true);
+        assert_eq!(0, set.current_card);
+        set.go_to_match("kot", false);
+        assert_eq!(2, set.current_card);
+        set.go_to(1);
+        set.go_to_match("kot", false);
+        assert_eq!(0, set.current_card);
+        set.go_to_match("pies", true);
+        assert_eq!(0, set.current_card);
+    }
+
+    #[test]
+    fn search_matches_skip_cards_hidden_by_filter() {
+        let mut cards = vec![
+            typed_card(0, "noun"),
+            typed_card(1, "verb"),
+            typed_card(2, "noun"),
+        ];
+        cards
+            .iter_mut()
+            .for_each(|card| card.sentence = "kot".to_string());
+        let mut set = CardSet::new("search", cards).filter(Filter {
+            types: vec!["noun".to_string()],
+            ..Filter::default()
+        });
+
+        set.go_to_match("kot", true);
+        assert_eq!(Some(2), set.current_idx());
+    }
+
+    #[test]
+    fn repeat_marks_are_toggled_in_bulk() {
+        let mut set = CardSet::new(
+            "bulk",
+            vec![gen_card_data(0), gen_card_data(1), gen_card_data(2)],
+        );
+        set.toggle_repeat_card();
+
+        assert!(set.toggle_repeat_cards(&[0, 2]));
+        assert_eq!(2, set.marked_cards().count());
+
+        assert!(!set.toggle_repeat_cards(&[0, 2]));
+        assert_eq!(0, set.marked_cards().count());
+    }
+
+    #[test]
+    fn edited_card_keeps_review_state() {
+        let mut set = card_set("regular set");
+        set.toggle_repeat_card();
+
+        let old = set.update_current_card(gen_card_data(5)).unwrap();
+
+        assert_eq!("word0", old.word);
+        assert_eq!(
+            Some("word5"),
+            set.get_current_card().map(|card| card.word.as_str())
+        );
+        assert!(set.repeat_current_card());
+        assert!(empty_card_set("empty set")
+            .update_current_card(gen_card_data(5))
+            .is_none());
+    }
+
+    #[test]
+    fn edited_card_keeps_its_progress() {
+        use crate::scheduler::progress::Progress;
+        use crate::scheduler::sm2::{Sm2, Sm2State};
+
+        let dir = tempfile::tempdir().unwrap();
+        let today = chrono::NaiveDate::from_ymd_opt(2020, 11, 1).unwrap();
+        let mut progress = Progress::load_for_deck(dir.path().join("deck.json")).unwrap();
+        progress.get_mut(&gen_card_data(0)).sm2 = Some(Sm2State::new(today));
+        let mut set = card_set("regular set").scheduler(Box::new(Sm2::new(progress, today)));
+        assert_eq!("new", set.card_stats(1));
+
+        set.update_current_card(gen_card_data(5));
+
+        assert!(set.card_stats(0).starts_with("due 2020-11-01"));
+    }
+
+    #[test]
+    fn card_is_inserted_after_current_or_at_end() {
+        let mut set = card_set("regular set");
+
+        assert_eq!(1, set.insert_card(gen_card_data(2), true));
+        assert_eq!(3, set.insert_card(gen_card_data(3), false));
+
+        let words: Vec<&str> = (0..4)
+            .filter_map(|position| set.card_at(position))
+            .map(|card| card.word.as_str())
+            .collect();
+        assert_eq!(vec!["word0", "word2", "word1", "word3"], words);
+        assert_eq!(0, set.current_card);
+    }
+
+    #[test]
+    fn filter_narrows_round_and_keeps_current_card() {
+        let cards = vec![
+            typed_card(0, "noun"),
+            typed_card(1, "verb"),
+            typed_card(2, "noun"),
+        ];
+        let mut set = CardSet::new("filtered", cards);
+        set.next_card();
+        set.next_card();
+        let nouns = Filter {
+            types: vec!["noun".to_string()],
+            ..Filter::default()
+        };
+
+        set.set_filter(nouns.clone());
+        assert_eq!(2, set.cards_len());
+        assert_eq!(
+            Some("word2"),
+            set.get_current_card().map(|card| card.word.as_str())
+        );
+
+        set.set_filter(Filter::default());
+        assert_eq!(3, set.cards_len());
+        assert_eq!(2, set.current_card);
+
+        set.go_to(1);
+        set.set_filter(nouns);
+        assert_eq!(
+            Some("word0"),
+            set.get_current_card().map(|card| card.word.as_str())
+        );
+    }
+
+    #[test]
+    fn card_added_while_filtered_stays_after_filter_is_cleared() {
+        let cards = vec![typed_card(0, "noun"), typed_card(1, "verb")];
+        let mut set = CardSet::new("filtered", cards).filter(Filter {
+            types: vec!["verb".to_string()],
+            ..Filter::default()
+        });
+
+        set.insert_card(typed_card(2, "verb"), true);
+        set.set_filter(Filter::default());
+
+        let words: Vec<&str> = (0..3)
+            .filter_map(|position| set.card_at(position))
+            .map(|card| card.word.as_str())
+            .collect();
+        assert_eq!(vec!["word0", "word1", "word2"], words);
+    }
+
+    #[test]
+    fn card_inserted_into_empty_set_becomes_current() {
+        let mut set = empty_card_set("empty set");
+
+        set.insert_card(gen_card_data(1), true);
+
+        assert_eq!(
+            Some("word1"),
+            set.get_current_card().map(|card| card.word.as_str())
+        );
+    }
+
+    #[test]
+    fn word_is_found_ignoring_case() {
         let set = card_set("regular set");
-        assert!(!set.repeat_current_card())
+
+        assert_eq!(
+            Some("word1"),
+            set.find_word(" WORD1 ").map(|card| card.word.as_str())
+        );
+        assert!(set.find_word("word7").is_none());
     }
 
     #[test]
-    fn repeat_card_when_toggled() {
-        let mut  set = card_set("regular set");
-        set.toggle_repeat_card();
+    fn go_to_is_clamped_to_last_card() {
+        let mut set = card_set("regular set");
 
-        assert!(set.repeat_current_card())
+        set.go_to(10);
+        assert_eq!(1, set.current_card);
+
+        set.go_to(0);
+        assert_eq!(0, set.current_card);
+    }
+
+    #[test]
+    fn go_to_in_empty_set_does_nothing() {
+        let mut set = empty_card_set("empty set");
+
+        set.go_to(3);
+
+        assert_eq!(0, set.current_card);
+        assert!(!set.is_round_finished());
     }
 
     #[test]
@@ -259,7 +1345,18 @@ mod tests {
         let set = card_set("set with title");
 
         assert_eq!(true, set.show_title);
-        assert_eq!(Some("set with title"), set.get_title());
+        assert_eq!(Some("set with title"), set.get_title().as_deref());
+    }
+
+    #[test]
+    fn title_shows_deck_of_current_card() {
+        let mut first = gen_card_data(0);
+        first.source = Some(PathBuf::from("decks/animals.json"));
+        let mut set = CardSet::new("2 decks", vec![first, gen_card_data(1)]);
+
+        assert_eq!(Some("decks/animals.json"), set.get_title().as_deref());
+        set.next_card();
+        assert_eq!(Some("2 decks"), set.get_title().as_deref());
     }
 
     #[test]
@@ -269,7 +1366,7 @@ mod tests {
 
         set.toggle_show_title();
 
-        assert_eq!(None, set.get_title());
+        assert_eq!(None, set.get_title().as_deref());
     }
 
     #[test]
@@ -487,6 +1584,454 @@ mod tests {
         assert_eq!(set.cards.len() - 1, set.current_card);
     }
 
+    #[test]
+    fn next_on_last_card_finishes_round() {
+        let mut set = card_set("new set");
+        set.next_card();
+        assert!(!set.is_round_finished());
+
+        set.next_card();
+
+        assert!(set.is_round_finished());
+    }
+
+    #[test]
+    fn prev_after_finishing_round_resumes_it() {
+        let mut set = card_set("new set");
+        set.current_card = 1;
+        set.next_card();
+
+        set.prev_card();
+
+        assert!(!set.is_round_finished());
+        assert_eq!(0, set.current_card);
+    }
+
+    #[test]
+    fn empty_set_never_finishes_round() {
+        let mut set = empty_card_set("empty set");
+
+        set.next_card();
+
+        assert!(!set.is_round_finished());
+    }
+
+    #[test]
+    fn no_next_round_when_nothing_marked() {
+        let mut set = card_set("new set");
+        set.current_card = 1;
+        set.next_card();
+
+        assert!(!set.start_next_round());
+        assert_eq!(1, set.round_nr());
+        assert_eq!(2, set.cards_len());
+    }
+
+    #[test]
+    fn next_round_contains_only_marked_cards() {
+        let mut set = CardSet::new(
+            "new set",
+            vec![gen_card_data(0), gen_card_data(1), gen_card_data(2)],
+        );
+        set.next_card();
+        set.toggle_repeat_card();
+        set.next_card();
+        set.next_card();
+        assert_eq!(1, set.cards_to_repeat());
+
+        assert!(set.start_next_round());
+
+        assert_eq!(2, set.round_nr());
+        assert_eq!(1, set.cards_len());
+        assert_eq!(0, set.current_card);
+        assert_eq!("word1", set.get_main_text());
+        assert!(!set.is_round_finished());
+        assert!(!set.repeat_current_card());
+    }
+
+    #[test]
+    fn cards_left_counts_cards_after_current() {
+        let mut set = card_set("new set");
+        assert_eq!(1, set.cards_left());
+
+        set.next_card();
+
+        assert_eq!(0, set.cards_left());
+    }
+
+    #[test]
+    fn card_id_depends_only_on_word_and_translation() {
+        let card = gen_card_data(0);
+        let mut edited = gen_card_data(0);
+        edited.explanation = "changed".to_string();
+        edited.sentence = "changed".to_string();
+
+        assert_eq!(card.id(), edited.id());
+        assert_ne!(card.id(), gen_card_data(1).id());
+    }
+
+    #[test]
+    fn card_id_is_stable() {
+        assert_eq!("c33d0e8b6f5111da", gen_card_data(0).id());
+    }
+
+    #[test]
+    fn only_revealed_cards_are_reviewed() {
+        let mut set = CardSet::new(
+            "new set",
+            vec![gen_card_data(0), gen_card_data(1), gen_card_data(2)],
+        );
+        set.next_card();
+        set.reverse_current_card();
+        set.reverse_current_card();
+        set.next_card();
+
+        assert_eq!(vec![("word1", Grade::Good)], reviewed_words(&set));
+    }
+
+    #[test]
+    fn jumping_to_a_card_does_not_review_it() {
+        let mut set = CardSet::new(
+            "new set",
+            vec![gen_card_data(0), gen_card_data(1), gen_card_data(2)],
+        );
+
+        set.go_to(2);
+        set.go_to(1);
+
+        assert!(reviewed_words(&set).is_empty());
+    }
+
+    #[test]
+    fn card_marked_in_any_round_is_failed() {
+        let mut set = card_set("new set");
+        set.reverse_current_card();
+        set.toggle_repeat_card();
+        set.next_card();
+        set.next_card();
+        set.start_next_round();
+        assert!(!set.repeat_current_card());
+
+        assert_eq!(
+            vec![("word0", Grade::Again), ("word1", Grade::Good)],
+            reviewed_words(&set)
+        );
+    }
+
+    #[test]
+    fn cant_grade_card_before_reversing() {
+        let mut set = card_set("new set");
+
+        set.grade_current_card(Grade::Easy);
+
+        assert!(set.answers().is_empty());
+        assert_eq!(0, set.current_card);
+    }
+
+    #[test]
+    fn reversed_cards_stay_revealed() {
+        let mut set = card_set("new set");
+        set.reverse_current_card();
+        set.reverse_current_card();
+        set.next_card();
+
+        assert!(!set.is_reversed());
+        assert!(set.cards[0].revealed);
+        assert!(!set.cards[1].revealed);
+    }
+
+    #[test]
+    fn grading_records_answer_and_moves_to_next_card() {
+        let mut set = card_set("new set");
+        set.reverse_current_card();
+
+        set.grade_current_card(Grade::Hard);
+
+        assert_eq!(&[(0, Grade::Hard)], set.answers());
+        assert_eq!(1, set.current_card);
+        assert!(!set.is_reversed());
+    }
+
+    #[test]
+    fn grading_again_marks_card_to_be_repeated() {
+        let mut set = card_set("new set");
+        set.reverse_current_card();
+        set.grade_current_card(Grade::Again);
+        set.reverse_current_card();
+        set.toggle_repeat_card();
+        set.grade_current_card(Grade::Good);
+
+        assert_eq!(1, set.cards_to_repeat());
+        assert!(set.is_round_finished());
+        assert!(set.start_next_round());
+        assert_eq!("word0", set.get_main_text());
+    }
+
+    #[test]
+    fn first_answer_in_session_is_used_for_review() {
+        let mut set = card_set("new set");
+        set.reverse_current_card();
+        set.grade_current_card(Grade::Again);
+        set.reverse_current_card();
+        set.grade_current_card(Grade::Easy);
+        set.start_next_round();
+        set.reverse_current_card();
+        set.grade_current_card(Grade::Good);
+
+        assert_eq!(
+            vec![("word0", Grade::Again), ("word1", Grade::Easy)],
+            reviewed_words(&set)
+        );
+        assert_eq!(3, set.answers().len());
+    }
+
+    #[test]
+    fn typed_answer_is_checked_only_in_type_mode() {
+        let mut set = card_set("new set");
+
+        set.check_typed_answer("translated0");
+
+        assert_eq!(None, set.get_typed_answer());
+        assert!(!set.is_reversed());
+    }
+
+    #[test]
+    fn correct_typed_answer_reverses_card() {
+        let mut set = card_set("new set").mode(Mode::Type);
+        assert!(set.awaits_typed_answer());
+
+        set.check_typed_answer("Translated0");
+
+        assert_eq!(
+            Some(("Translated0", Match::IgnoringCase)),
+            set.get_typed_answer()
+        );
+        assert!(set.is_reversed());
+        assert!(!set.repeat_current_card());
+        assert!(!set.awaits_typed_answer());
+    }
+
+    #[test]
+    fn wrong_typed_answer_marks_card_to_be_repeated() {
+        let mut set = card_set("new set").mode(Mode::Type);
+
+        set.check_typed_answer("something else");
+
+        assert!(set.repeat_current_card());
+    }
+
+    #[test]
+    fn moving_to_next_card_clears_typed_answer() {
+        let mut set = card_set("new set").mode(Mode::Type);
+        set.check_typed_answer("translated0");
+
+        set.next_card();
+
+        assert_eq!(None, set.get_typed_answer());
+        assert!(!set.is_reversed());
+        assert!(set.awaits_typed_answer());
+    }
+
+    #[test]
+    fn cloze_from_gap_fields() {
+        let mut card = gen_card_data(0);
+        card.sentence = "The cat sat.".to_string();
+        card.sentence_gap = Some("The ___ sat.".to_string());
+        card.gap_term = Some("cat".to_string());
+
+        assert_eq!(
+            Some(Cloze {
+                sentence_gap: "The ___ sat.".to_string(),
+                sentence: "The cat sat.".to_string(),
+                term: "cat".to_string(),
+            }),
+            card.cloze()
+        );
+    }
+
+    #[test]
+    fn cloze_gap_is_filled_when_no_sentence() {
+        let mut card = gen_card_data(0);
+        card.sentence = "".to_string();
+        card.sentence_gap = Some("The ______ sat.".to_string());
+
+        assert_eq!("The word0 sat.", card.cloze().unwrap().sentence);
+    }
+
+    #[test]
+    fn cloze_generated_from_word_in_sentence() {
+        let mut card = gen_card_data(0);
+        card.word = "żółw".to_string();
+        card.sentence = "Żółw pływa.".to_string();
+
+        assert_eq!(
+            Some(Cloze {
+                sentence_gap: "______ pływa.".to_string(),
+                sentence: "Żółw pływa.".to_string(),
+                term: "Żółw".to_string(),
+            }),
+            card.cloze()
+        );
+    }
+
+    #[test]
+    fn no_cloze_when_word_not_in_sentence() {
+        assert_eq!(None, gen_card_data(0).cloze());
+    }
+
+    #[test]
+    fn cloze_mode_shows_gap_and_reveals_sentence() {
+        let mut card = gen_card_data(0);
+        card.sentence = "A word0 here.".to_string();
+        let mut set = CardSet::new("cloze set", vec![card]).mode(Mode::Cloze);
+        set.show_example = true;
+
+        assert_eq!("A ______ here.", set.get_main_text());
+        assert_eq!(None, set.get_example());
+        assert_eq!(None, set.get_revealed_term());
+
+        set.reverse_current_card();
+
+        assert_eq!("A word0 here.", set.get_main_text());
+        assert_eq!(Some("word0".to_string()), set.get_revealed_term());
+    }
+
+    #[test]
+    fn cloze_mode_falls_back_to_flipping_without_sentence() {
+        let mut set = card_set("cloze set").mode(Mode::Cloze);
+
+        assert_eq!("word0", set.get_main_text());
+        set.reverse_current_card();
+        assert_eq!("translated0", set.get_main_text());
+    }
+
+    #[test]
+    fn optional_fields_are_read_from_json() {
+        let cards: Vec<CardData> =
+            serde_json::from_str(include_str!("../../examples/animals.json")).unwrap();
+
+        let card = &cards[0];
+        assert_eq!(Some("noun"), card.card_type.as_deref());
+        assert!(card.sentence_gap.is_some());
+        assert!(card.gap_term.is_some());
+    }
+
+    fn typed_card(nr: i8, card_type: &str) -> CardData {
+        let mut card = gen_card_data(nr);
+        card.card_type = Some(card_type.to_string());
+        card
+    }
+
+    #[test]
+    fn no_choices_outside_choice_mode() {
+        let set = card_set("new set");
+
+        assert!(!set.awaits_choice());
+        assert!(set.get_choices().is_empty());
+    }
+
+    #[test]
+    fn choices_contain_correct_answer_and_distinct_distractors() {
+        let mut cards: Vec<CardData> = (0..6).map(gen_card_data).collect();
+        cards[5].translated = "translated1".to_string();
+        let set = CardSet::new("new set", cards).mode(Mode::Choice);
+
+        let choices = set.get_choices();
+
+        assert_eq!(4, choices.len());
+        assert_eq!(1, choices.iter().filter(|c| c.correct).count());
+        assert!(choices.iter().any(|c| c.text == "translated0"));
+        let mut texts: Vec<&str> = choices.iter().map(|c| c.text).collect();
+        texts.sort();
+        texts.dedup();
+        assert_eq!(4, texts.len());
+    }
+
+    #[test]
+    fn choices_prefer_distractors_of_same_type() {
+        let cards = vec![
+            typed_card(0, "noun"),
+            typed_card(1, "verb"),
+            typed_card(2, "noun"),
+            typed_card(3, "verb"),
+            typed_card(4, "noun"),
+            typed_card(5, "noun"),
+            typed_card(6, "verb"),
+        ];
+        let set = CardSet::new("new set", cards).mode(Mode::Choice);
+
+        let mut texts: Vec<&str> = set.get_choices().iter().map(|c| c.text).collect();
+        texts.sort();
+
+        assert_eq!(
+            vec!["translated0", "translated2", "translated4", "translated5"],
+            texts
+        );
+    }
+
+    #[test]
+    fn small_set_has_fewer_choices() {
+        let set = card_set("new set").mode(Mode::Choice);
+
+        assert_eq!(2, set.get_choices().len());
+    }
+
+    #[test]
+    fn wrong_choice_marks_card_to_be_repeated() {
+        let mut set = card_set("new set").mode(Mode::Choice);
+        let wrong = set.get_choices().iter().position(|c| !c.correct).unwrap();
+
+        set.choose(wrong);
+
+        assert!(!set.awaits_choice());
+        assert!(set.is_reversed());
+        assert!(set.repeat_current_card());
+        assert!(set.get_choices()[wrong].chosen);
+    }
+
+    #[test]
+    fn correct_choice_and_moving_on() {
+        let mut set = card_set("new set").mode(Mode::Choice);
+        let correct = set.get_choices().iter().position(|c| c.correct).unwrap();
+
+        set.choose(correct);
+        assert!(!set.repeat_current_card());
+
+        set.next_card();
+        assert!(set.awaits_choice());
+        assert!(!set.is_reversed());
+        assert!(set
+            .get_choices()
+            .iter()
+            .any(|c| c.text == "translated1" && c.correct));
+    }
+
+    #[derive(Debug)]
+    struct OddCardsDue;
+
+    impl Scheduler for OddCardsDue {
+        fn is_due(&self, card: &CardData) -> bool {
+            card.word
+                .ends_with(|c: char| c.to_digit(10).is_some_and(|d| d % 2 == 1))
+        }
+
+        fn record(&mut self, _card: &CardData, _grade: Grade) {}
+
+        fn save(&self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn scheduler_picks_due_cards_for_round() {
+        let cards = (0..5).map(gen_card_data).collect();
+        let mut set = CardSet::new("new set", cards).scheduler(Box::new(OddCardsDue));
+        set.reverse_current_card();
+
+        assert_eq!(2, set.cards_len());
+        assert_eq!(vec![("word1", Grade::Good)], reviewed_words(&set));
+    }
+
     #[test]
     fn going_zen_doesnt_change_other_settings_but_displays_only_essential() {
         let mut set = card_set("new set");
@@ -660,7 +2205,7 @@ mod tests {
 
         assert_eq!(
             -1,
-            set.count_view_weight(usize::max_value(), usize::max_value(), usize::max_value())
+            set.count_view_weight(usize::MAX, usize::MAX, usize::MAX)
         );
     }
 
@@ -670,10 +2215,7 @@ mod tests {
         hide_everything(&mut set);
         set.show_hints = true;
 
-        assert_eq!(
-            1,
-            set.count_view_weight(usize::max_value(), usize::max_value(), usize::max_value())
-        );
+        assert_eq!(1, set.count_view_weight(usize::MAX, usize::MAX, usize::MAX));
     }
 
     #[test]
@@ -682,10 +2224,7 @@ mod tests {
         hide_everything(&mut set);
         set.show_description = true;
 
-        assert_eq!(
-            1,
-            set.count_view_weight(usize::max_value(), 0, usize::max_value())
-        );
+        assert_eq!(1, set.count_view_weight(usize::MAX, 0, usize::MAX));
     }
 
     #[test]
@@ -694,7 +2233,7 @@ mod tests {
         hide_everything(&mut set);
         set.show_description = true;
 
-        assert_eq!(2, set.count_view_weight(10, 10, usize::max_value()));
+        assert_eq!(2, set.count_view_weight(10, 10, usize::MAX));
     }
 
     #[test]
@@ -703,10 +2242,7 @@ mod tests {
         hide_everything(&mut set);
         set.show_example = true;
 
-        assert_eq!(
-            1,
-            set.count_view_weight(usize::max_value(), usize::max_value(), 0)
-        );
+        assert_eq!(1, set.count_view_weight(usize::MAX, usize::MAX, 0));
     }
 
     #[test]
@@ -715,7 +2251,7 @@ mod tests {
         hide_everything(&mut set);
         set.show_example = true;
 
-        assert_eq!(2, set.count_view_weight(10, usize::max_value(), 10));
+        assert_eq!(2, set.count_view_weight(10, usize::MAX, 10));
     }
 
     fn gen_card_data(nr: i8) -> CardData {
@@ -725,10 +2261,26 @@ mod tests {
             explanation: format!("explanation{}", nr),
             pronunciation: format!("pronunciation{}", nr),
             sentence: format!("sentence{}", nr),
+            card_type: None,
+            sentence_gap: None,
+            gap_term: None,
+            tags: vec![],
             to_be_repeated: false,
+            seen: false,
+            lapsed: false,
+            revealed: false,
+            source: None,
+            position: None,
         }
     }
 
+    fn reviewed_words(set: &CardSet) -> Vec<(&str, Grade)> {
+        set.reviewed()
+            .into_iter()
+            .map(|(idx, grade)| (set.cards[idx].word.as_str(), grade))
+            .collect()
+    }
+
     fn show_everything(set: &mut CardSet) {
         set.show_example = true;
         set.show_description = true;
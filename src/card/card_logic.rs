@@ -1,20 +1,391 @@
 use core::option::Option::Some;
 use std::cell::{RefCell, RefMut};
+use std::path::PathBuf;
 use std::rc::Rc;
+use std::time::Instant;
 
 use cursive::Cursive;
 
-use crate::card::card_data::CardSet;
-use crate::card::card_ui::display;
+use crate::browser::{self, BROWSER_DIALOG};
+use crate::card::card_data::{CardData, CardSet, Grade};
+use crate::card::card_ui::{
+    display, show_end_of_round, show_filter, show_go_to_card, show_search, show_summary,
+    END_OF_ROUND_DIALOG, FILTER_DIALOG, GO_TO_CARD_DIALOG, SEARCH_DIALOG, SUMMARY_DIALOG,
+};
+use crate::card::filter::Filter;
+use crate::deck::rewrite;
+use crate::editor::{self, DUPLICATE_DIALOG, EDITOR_DIALOG, NEW_CARD_DIALOG, SAVE_AS_DIALOG};
 use crate::help::HELP_DIALOG;
+use crate::shortcuts::Keymap;
+use crate::summary::Summary;
 use cursive::views::Dialog;
 
-pub fn next_card(siv: &mut Cursive) {
-    update_card_set(siv, |card_set| card_set.next_card());
+const PAGE_SIZE: usize = 10;
+
+pub struct UserData {
+    pub card_set: Rc<RefCell<CardSet>>,
+    pub keymap: Rc<Keymap>,
+    pub count: Option<usize>,
+    pub search: Option<String>,
+    // JSON file cards without a deck of their own are written to, set by "save as"
+    pub deck: Option<PathBuf>,
+    // JSON decks that cards read from them are written back to
+    pub json_decks: Vec<PathBuf>,
+    // Set to leave the event loop and edit the current card in $EDITOR
+    pub external_edit: bool,
+    pub started: Instant,
+}
+
+pub fn keymap(siv: &mut Cursive) -> Rc<Keymap> {
+    siv.user_data::<UserData>()
+        .map(|data| data.keymap.clone())
+        .unwrap_or_default()
+}
+
+fn card_set(siv: &mut Cursive) -> Option<Rc<RefCell<CardSet>>> {
+    siv.user_data::<UserData>()
+        .map(|data| data.card_set.clone())
+}
+
+// Digits typed before a shortcut make its count, e.g. "25l" or "40g"
+pub fn add_to_count(siv: &mut Cursive, digit: usize) {
+    if dialog_shown(siv) {
+        return;
+    }
+    if let Some(data) = siv.user_data::<UserData>() {
+        data.count = Some(extend_count(data.count, digit));
+    }
+    refresh(siv);
+}
+
+fn extend_count(count: Option<usize>, digit: usize) -> usize {
+    count.unwrap_or(0).saturating_mul(10).saturating_add(digit)
+}
+
+pub fn pending_count(siv: &mut Cursive) -> Option<usize> {
+    siv.user_data::<UserData>().and_then(|data| data.count)
+}
+
+// Shortcuts without a count drop it, the hints bar shows it is gone
+pub fn take_count(siv: &mut Cursive) -> Option<usize> {
+    let count = siv
+        .user_data::<UserData>()
+        .and_then(|data| data.count.take());
+    if count.is_some() {
+        refresh(siv);
+    }
+    count
+}
+
+pub fn awaits_choice(siv: &mut Cursive) -> bool {
+    card_set(siv).is_some_and(|card_set| card_set.borrow().awaits_choice())
+}
+
+pub fn next_card(siv: &mut Cursive, count: Option<usize>) {
+    match count {
+        Some(count) => update_card_set(siv, |card_set| {
+            card_set.go_to(card_set.current_card.saturating_add(count))
+        }),
+        None => {
+            update_card_set(siv, |card_set| card_set.next_card());
+            show_end_of_round_if_finished(siv);
+        }
+    }
+}
+
+pub fn prev_card(siv: &mut Cursive, count: Option<usize>) {
+    match count {
+        Some(count) => update_card_set(siv, |card_set| {
+            card_set.go_to(card_set.current_card.saturating_sub(count))
+        }),
+        None => update_card_set(siv, |card_set| card_set.prev_card()),
+    }
+}
+
+// With a count both go to the card with that number
+pub fn first_card(siv: &mut Cursive, count: Option<usize>) {
+    go_to(siv, count.unwrap_or(1));
+}
+
+pub fn last_card(siv: &mut Cursive, count: Option<usize>) {
+    go_to(siv, count.unwrap_or(usize::MAX));
+}
+
+pub fn page_back(siv: &mut Cursive, count: Option<usize>) {
+    let step = count.unwrap_or(1).saturating_mul(PAGE_SIZE);
+    update_card_set(siv, |card_set| {
+        card_set.go_to(card_set.current_card.saturating_sub(step))
+    });
+}
+
+pub fn page_forward(siv: &mut Cursive, count: Option<usize>) {
+    let step = count.unwrap_or(1).saturating_mul(PAGE_SIZE);
+    update_card_set(siv, |card_set| {
+        card_set.go_to(card_set.current_card.saturating_add(step))
+    });
+}
+
+// Cards are numbered from 1, as in the title bar
+pub fn go_to(siv: &mut Cursive, card_nr: usize) {
+    update_card_set(siv, |card_set| card_set.go_to(card_nr.saturating_sub(1)));
+}
+
+pub fn go_to_card(siv: &mut Cursive) {
+    if dialog_shown(siv) {
+        return;
+    }
+    if let Some(card_set) = card_set(siv) {
+        show_go_to_card(siv, &card_set.borrow());
+    }
+}
+
+pub fn filter(siv: &mut Cursive) {
+    if dialog_shown(siv) {
+        return;
+    }
+    if let Some(card_set) = card_set(siv) {
+        show_filter(siv, &card_set.borrow());
+    }
+}
+
+pub fn set_filter(siv: &mut Cursive, filter: Filter) {
+    update_card_set(siv, |card_set| card_set.set_filter(filter));
+}
+
+pub fn search(siv: &mut Cursive) {
+    if !dialog_shown(siv) {
+        show_search(siv);
+    }
+}
+
+pub fn search_results(siv: &mut Cursive, query: &str) -> Vec<(String, usize)> {
+    match card_set(siv) {
+        Some(card_set) => {
+            let card_set = card_set.borrow();
+            card_set
+                .search(query)
+                .into_iter()
+                .map(|idx| {
+                    let card = &card_set.cards()[idx];
+                    let place = if card_set.position_of(idx).is_some() {
+                        ""
+                    } else if card_set.hidden_by_filter(idx) {
+                        " (filtered out)"
+                    } else {
+                        " (not in this round)"
+                    };
+                    (format!("{} - {}{}", card.word, card.translated, place), idx)
+                })
+                .collect()
+        }
+        None => vec![],
+    }
+}
+
+pub fn jump_to_match(siv: &mut Cursive, query: &str, idx: usize) {
+    if let Some(data) = siv.user_data::<UserData>() {
+        data.search = Some(query.to_string());
+    }
+    show_card(siv, idx);
+}
+
+// Cards hidden by the filter are shown without it, the rest of the cards
+// outside of the round cannot be reached
+pub fn show_card(siv: &mut Cursive, idx: usize) {
+    let card_set = match card_set(siv) {
+        Some(card_set) => card_set,
+        None => return,
+    };
+    let shown = card_set.borrow_mut().show_card(idx);
+    display(siv, &card_set.borrow());
+    if !shown {
+        let word = card_set.borrow().cards()[idx].word.clone();
+        siv.add_layer(Dialog::info(format!("'{}' is not in this round", word)));
+    }
+}
+
+// Cycles through the matches of the last search; without one it moves like next/prev card
+pub fn next_match(siv: &mut Cursive, forward: bool, count: Option<usize>) {
+    match siv
+        .user_data::<UserData>()
+        .and_then(|data| data.search.clone())
+    {
+        Some(query) => update_card_set(siv, |card_set| card_set.go_to_match(&query, forward)),
+        None if forward => next_card(siv, count),
+        None => prev_card(siv, count),
+    }
 }
 
-pub fn prev_card(siv: &mut Cursive) {
-    update_card_set(siv, |card_set| card_set.prev_card());
+pub fn show_browser(siv: &mut Cursive) {
+    if dialog_shown(siv) {
+        return;
+    }
+    if let Some(card_set) = card_set(siv) {
+        browser::show_browser(siv, &card_set.borrow());
+    }
+}
+
+// Used from the card browser, so it does not wait for the dialogs to close
+pub fn toggle_repeat_cards(siv: &mut Cursive, idxs: &[usize]) -> bool {
+    match card_set(siv) {
+        Some(card_set) => {
+            let mut card_set = card_set.borrow_mut();
+            let marked = card_set.toggle_repeat_cards(idxs);
+            display(siv, &card_set);
+            marked
+        }
+        None => false,
+    }
+}
+
+pub fn edit_card(siv: &mut Cursive) {
+    if dialog_shown(siv) {
+        return;
+    }
+    if let Some(card_set) = card_set(siv) {
+        if let Some(card) = card_set.borrow().get_current_card() {
+            editor::show_editor(siv, card);
+        }
+    }
+}
+
+pub fn edit_card_externally(siv: &mut Cursive) {
+    if dialog_shown(siv) {
+        return;
+    }
+    let has_card =
+        card_set(siv).is_some_and(|card_set| card_set.borrow().get_current_card().is_some());
+    if let Some(data) = siv.user_data::<UserData>().filter(|_| has_card) {
+        data.external_edit = true;
+        siv.quit();
+    }
+}
+
+// Cards of CSV/TSV decks or stdin have no JSON file of their own
+fn deck_file(siv: &mut Cursive, card: &CardData) -> Option<PathBuf> {
+    let data = siv.user_data::<UserData>()?;
+    match &card.source {
+        Some(source) if data.json_decks.contains(source) => Some(source.clone()),
+        _ => data.deck.clone(),
+    }
+}
+
+pub fn take_external_edit(siv: &mut Cursive) -> bool {
+    siv.user_data::<UserData>()
+        .is_some_and(|data| std::mem::take(&mut data.external_edit))
+}
+
+pub fn save_card(siv: &mut Cursive, card: CardData) {
+    let card_set = match card_set(siv) {
+        Some(card_set) => card_set,
+        None => return,
+    };
+    let old = match card_set.borrow_mut().update_current_card(card) {
+        Some(old) => old,
+        None => return,
+    };
+    display(siv, &card_set.borrow());
+
+    match deck_file(siv, &old) {
+        Some(path) => {
+            let card_set = card_set.borrow();
+            if let Some(card) = card_set.get_current_card() {
+                if let Err(e) = rewrite::update_card(&path, &old, card) {
+                    siv.add_layer(Dialog::info(e.to_string()));
+                }
+            }
+        }
+        None => editor::show_save_as(siv),
+    }
+}
+
+pub fn new_card(siv: &mut Cursive) {
+    if dialog_shown(siv) {
+        return;
+    }
+    editor::show_new_card(siv);
+}
+
+pub fn find_word(siv: &mut Cursive, word: &str) -> Option<CardData> {
+    card_set(siv).and_then(|card_set| card_set.borrow().find_word(word).cloned())
+}
+
+pub fn add_card(siv: &mut Cursive, mut card: CardData, after_current: bool) {
+    let card_set = match card_set(siv) {
+        Some(card_set) => card_set,
+        None => return,
+    };
+    // a new card goes to the deck of the card it was added at
+    card.source = card_set
+        .borrow()
+        .get_current_card()
+        .and_then(|current| current.source.clone());
+    let path = deck_file(siv, &card);
+    let appended = path.as_ref().map(|path| rewrite::append_card(path, &card));
+    if let Some(Ok(position)) = appended {
+        card.position = Some(position);
+    }
+    card_set.borrow_mut().insert_card(card, after_current);
+    display(siv, &card_set.borrow());
+
+    match appended {
+        Some(Err(e)) => siv.add_layer(Dialog::info(e.to_string())),
+        Some(Ok(_)) => {}
+        None => editor::show_save_as(siv),
+    }
+}
+
+// Cards of JSON decks stay in them, only the rest is saved
+pub fn save_deck_as(siv: &mut Cursive, path: &str) {
+    let (card_set, json_decks) = match siv.user_data::<UserData>() {
+        Some(data) => (data.card_set.clone(), data.json_decks.clone()),
+        None => return,
+    };
+    let saved: Vec<usize> = card_set
+        .borrow()
+        .cards()
+        .iter()
+        .enumerate()
+        .filter(|(_, card)| {
+            !card
+                .source
+                .as_ref()
+                .is_some_and(|source| json_decks.contains(source))
+        })
+        .map(|(idx, _)| idx)
+        .collect();
+    let cards: Vec<CardData> = saved
+        .iter()
+        .map(|idx| card_set.borrow().cards()[*idx].clone())
+        .collect();
+    match rewrite::write_deck(path, &cards) {
+        Ok(()) => {
+            card_set.borrow_mut().set_positions(&saved);
+            if let Some(data) = siv.user_data::<UserData>() {
+                data.deck = Some(PathBuf::from(path));
+            }
+        }
+        Err(e) => siv.add_layer(Dialog::info(format!("Cannot save {}: {}", path, e))),
+    }
+}
+
+pub fn grade_card(siv: &mut Cursive, grade: Grade) {
+    update_card_set(siv, |card_set| card_set.grade_current_card(grade));
+    show_end_of_round_if_finished(siv);
+}
+
+pub fn choose(siv: &mut Cursive, nr: usize) {
+    update_card_set(siv, |card_set| card_set.choose(nr));
+}
+
+pub fn check_typed_answer(siv: &mut Cursive, answer: &str) {
+    update_card_set(siv, |card_set| card_set.check_typed_answer(answer));
+}
+
+pub fn start_next_round(siv: &mut Cursive) {
+    update_card_set(siv, |card_set| {
+        card_set.start_next_round();
+    });
 }
 
 pub fn reverse_card(siv: &mut Cursive) {
@@ -53,16 +424,74 @@ pub fn refresh(siv: &mut Cursive) {
     update_card_set(siv, |_| {});
 }
 
+fn show_end_of_round_if_finished(siv: &mut Cursive) {
+    if let Some(card_set) = card_set(siv) {
+        let card_set = card_set.borrow();
+        if card_set.is_round_finished() {
+            show_end_of_round(siv, &card_set);
+        }
+    }
+}
+
+// The summary is shown first, quitting from it leaves the app
+pub fn quit(siv: &mut Cursive) {
+    if summary_shown(siv) {
+        siv.quit();
+        return;
+    }
+    let summary = match siv.user_data::<UserData>() {
+        Some(data) => Summary::of(&data.card_set.borrow(), data.started.elapsed()),
+        None => return siv.quit(),
+    };
+    show_summary(siv, &summary);
+}
+
+pub fn summary_shown(siv: &mut Cursive) -> bool {
+    siv.find_name::<Dialog>(SUMMARY_DIALOG).is_some()
+}
+
+pub fn dialog_shown(siv: &mut Cursive) -> bool {
+    [
+        HELP_DIALOG,
+        END_OF_ROUND_DIALOG,
+        GO_TO_CARD_DIALOG,
+        SEARCH_DIALOG,
+        FILTER_DIALOG,
+        BROWSER_DIALOG,
+        EDITOR_DIALOG,
+        NEW_CARD_DIALOG,
+        DUPLICATE_DIALOG,
+        SAVE_AS_DIALOG,
+        SUMMARY_DIALOG,
+    ]
+    .iter()
+    .any(|name| siv.find_name::<Dialog>(name).is_some())
+}
+
 fn update_card_set<F>(siv: &mut Cursive, cb: F)
 where
     F: FnOnce(&mut CardSet),
 {
-    if let Some(_) = siv.find_name::<Dialog>(HELP_DIALOG) {
+    if dialog_shown(siv) {
         return;
     }
-    if let Some(card_set) = siv.user_data::<Rc<RefCell<CardSet>>>().cloned() {
+    if let Some(card_set) = card_set(siv) {
         let mut card_set: RefMut<CardSet> = RefCell::borrow_mut(&card_set);
         cb(&mut card_set);
         display(siv, &card_set);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn digits_extend_the_count() {
+        assert_eq!(2, extend_count(None, 2));
+        assert_eq!(25, extend_count(Some(2), 5));
+        assert_eq!(0, extend_count(None, 0));
+        assert_eq!(25, extend_count(Some(extend_count(None, 0)), 25));
+        assert_eq!(usize::MAX, extend_count(Some(usize::MAX), 9));
+    }
+}
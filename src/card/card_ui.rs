@@ -1,52 +1,122 @@
 use cursive::align::HAlign;
 use cursive::align::VAlign;
 use cursive::Cursive;
-use cursive::theme::{ColorStyle, ColorType, Effect, PaletteColor};
+use cursive::theme::{BaseColor, Color, ColorStyle, ColorType, Effect, PaletteColor, Style};
 use cursive::traits::*;
+use cursive::utils::markup::StyledString;
 use cursive::view::SizeConstraint;
-use cursive::views::{Layer, LinearLayout, NamedView, ResizedView, TextView, ViewRef};
+use cursive::views::{
+    Dialog, DummyView, EditView, Layer, LinearLayout, NamedView, ResizedView, SelectView, TextView,
+    ViewRef,
+};
 
-use crate::card::card_data::CardSet;
+use crate::answer::{self, DiffChar};
+use crate::card::card_data::{self, CardSet, Mode};
+use crate::card::card_logic;
+use crate::shortcuts::{Keymap, Shortcut};
+use crate::summary::{Answers, Summary};
 
-const HINTS_TEXT: &'static str = "| q:quit | ?:help |";
-const DESCRIPTION_PREFIX: &'static str = "Description: ";
-const EXAMPLE_PREFIX: &'static str = "Example: ";
+const HINTS: &[(Shortcut, &str)] = &[(Shortcut::Quit, "quit"), (Shortcut::Help, "help")];
+const TYPE_HINTS_TEXT: &str = "| enter:check answer |";
+const GRADE_HINTS: &[(Shortcut, &str)] = &[
+    (Shortcut::GradeAgain, "again"),
+    (Shortcut::GradeHard, "hard"),
+    (Shortcut::GradeGood, "good"),
+    (Shortcut::GradeEasy, "easy"),
+];
+const DESCRIPTION_PREFIX: &str = "Description: ";
+const EXAMPLE_PREFIX: &str = "Example: ";
 
-const TITLE_BAR: &'static str = "title_bar";
-const TOP_SPACER: &'static str = "top_spacer";
-const TERM: &'static str = "term";
-const PRONUNCIATION: &'static str = "pronunciation";
-const BOTTOM_SPACER: &'static str = "bottom_spacer";
-const DESCRIPTION: &'static str = "description";
-const EXAMPLE: &'static str = "example";
-const HINTS_BAR: &'static str = "hints_bar";
+const TITLE_BAR: &str = "title_bar";
+const TOP_SPACER: &str = "top_spacer";
+const TERM: &str = "term";
+const PRONUNCIATION: &str = "pronunciation";
+const BOTTOM_SPACER: &str = "bottom_spacer";
+const DESCRIPTION: &str = "description";
+const EXAMPLE: &str = "example";
+const HINTS_BAR: &str = "hints_bar";
+const ANSWER: &str = "answer";
+const ANSWER_RESULT: &str = "answer_result";
+const CHOICES: &str = "choices";
+
+const ANSWER_WIDTH: usize = 40;
+const CORRECT_COLOR: Color = Color::Light(BaseColor::Green);
+const WRONG_COLOR: Color = Color::Light(BaseColor::Red);
+
+pub const END_OF_ROUND_DIALOG: &str = "end_of_round_dialog";
+pub const SUMMARY_DIALOG: &str = "summary_dialog";
+pub const GO_TO_CARD_DIALOG: &str = "go_to_card_dialog";
+const GO_TO_CARD_WIDTH: usize = 20;
+pub const SEARCH_DIALOG: &str = "search_dialog";
+const SEARCH_QUERY: &str = "search_query";
+const SEARCH_RESULTS: &str = "search_results";
+const SEARCH_WIDTH: usize = 50;
+const SEARCH_HEIGHT: usize = 10;
+pub const FILTER_DIALOG: &str = "filter_dialog";
+const FILTER_WIDTH: usize = 50;
+const FILTER_HELP: &str = "Tags, -excluded tags, type:TYPE, deck:DECK\nEmpty shows all cards";
 
 pub fn setup_layout(siv: &mut Cursive, card_set: &CardSet) {
+    let mut layout = LinearLayout::vertical()
+        .child(reverse_color_row(TextView::new(""), TITLE_BAR))
+        .child(TextView::new(" ").fixed_height(0).with_name(TOP_SPACER))
+        .child(
+            TextView::new("")
+                .center()
+                .effect(Effect::Bold)
+                .v_align(VAlign::Bottom)
+                .resized(SizeConstraint::Full, SizeConstraint::Full)
+                .with_name(TERM),
+        )
+        .child(
+            TextView::new("")
+                .center()
+                .v_align(VAlign::Bottom)
+                .fixed_height(2)
+                .with_name(PRONUNCIATION),
+        );
+
+    if card_set.get_mode() == Mode::Type {
+        layout.add_child(
+            LinearLayout::horizontal()
+                .child(DummyView.full_width())
+                .child(
+                    EditView::new()
+                        .on_submit(card_logic::check_typed_answer)
+                        .with_name(ANSWER)
+                        .fixed_width(ANSWER_WIDTH),
+                )
+                .child(DummyView.full_width()),
+        );
+        layout.add_child(
+            TextView::new("")
+                .center()
+                .fixed_height(2)
+                .with_name(ANSWER_RESULT),
+        );
+    }
+
+    if card_set.get_mode() == Mode::Choice {
+        layout.add_child(
+            LinearLayout::horizontal()
+                .child(DummyView.full_width())
+                .child(
+                    TextView::new("")
+                        .with_name(CHOICES)
+                        .fixed_width(ANSWER_WIDTH),
+                )
+                .child(DummyView.full_width()),
+        );
+    }
+
     siv.add_fullscreen_layer(
-        LinearLayout::vertical()
-            .child(reverse_color_row(TextView::new(""), TITLE_BAR))
-            .child(TextView::new(" ").fixed_height(0).with_name(TOP_SPACER))
-            .child(
-                TextView::new("")
-                    .center()
-                    .effect(Effect::Bold)
-                    .v_align(VAlign::Bottom)
-                    .resized(SizeConstraint::Full, SizeConstraint::Full)
-                    .with_name(TERM),
-            )
-            .child(
-                TextView::new("")
-                    .center()
-                    .v_align(VAlign::Bottom)
-                    .fixed_height(2)
-                    .with_name(PRONUNCIATION),
-            )
+        layout
             .child(TextView::new(" ").resized(SizeConstraint::Full, SizeConstraint::Full))
             .child(TextView::new(" ").with_name(BOTTOM_SPACER))
             .child(TextView::new(" ").max_height(2).with_name(DESCRIPTION))
             .child(TextView::new(" ").max_height(2).with_name(EXAMPLE))
             .child(reverse_color_row(
-                TextView::new(HINTS_TEXT).h_align(HAlign::Right),
+                TextView::new("").h_align(HAlign::Right),
                 HINTS_BAR,
             ))
             .resized(SizeConstraint::Full, SizeConstraint::Full),
@@ -68,21 +138,35 @@ fn reverse_color_row(view: TextView, view_name: &str) -> Layer<NamedView<Resized
 }
 
 pub fn display(siv: &mut Cursive, card_set: &CardSet) {
-    update_optional_view(siv, TITLE_BAR, &card_set.get_title(), |view, text| {
-        let title = format!(
-            "{}: {}/{}",
-            text,
-            card_set.current_card + 1,
-            card_set.cards_len()
-        );
-        let title = if card_set.repeat_current_card() {
-            format!("{} TO BE REPEATED", title)
-        } else {
-            title
-        };
-        view.get_inner_mut().set_content(title);
-        view.set_height(SizeConstraint::Fixed(1));
-    });
+    let keymap = card_logic::keymap(siv);
+    let count = card_logic::pending_count(siv);
+    update_optional_view(
+        siv,
+        TITLE_BAR,
+        &card_set.get_title().as_deref(),
+        |view, text| {
+            let title = format!(
+                "{}: {}/{} | round {}, {} left",
+                text,
+                card_set.current_card + 1,
+                card_set.cards_len(),
+                card_set.round_nr(),
+                card_set.cards_left()
+            );
+            let title = if card_set.get_filter().is_empty() {
+                title
+            } else {
+                format!("{} | filter: {}", title, card_set.get_filter())
+            };
+            let title = if card_set.repeat_current_card() {
+                format!("{} TO BE REPEATED", title)
+            } else {
+                title
+            };
+            view.get_inner_mut().set_content(title);
+            view.set_height(SizeConstraint::Fixed(1));
+        },
+    );
 
     let weight = card_set.count_view_weight(
         siv.screen_size().x,
@@ -93,7 +177,10 @@ pub fn display(siv: &mut Cursive, card_set: &CardSet) {
     update_weight(siv, weight, TOP_SPACER);
 
     if let Some(mut view) = siv.find_name::<ResizedView<TextView>>(TERM) {
-        view.get_inner_mut().set_content(card_set.get_main_text())
+        view.get_inner_mut().set_content(highlight_term(
+            &card_set.get_main_text(),
+            card_set.get_revealed_term(),
+        ))
     }
 
     update_optional_view(
@@ -106,6 +193,9 @@ pub fn display(siv: &mut Cursive, card_set: &CardSet) {
         },
     );
 
+    update_typed_answer(siv, card_set);
+    update_choices(siv, card_set);
+
     update_weight(siv, -weight, BOTTOM_SPACER);
 
     update_optional_view(siv, DESCRIPTION, &card_set.get_desc(), |view, text| {
@@ -122,6 +212,21 @@ pub fn display(siv: &mut Cursive, card_set: &CardSet) {
 
     if let Some(mut view) = siv.find_name::<ResizedView<TextView>>(HINTS_BAR) {
         if card_set.show_hints() {
+            let hints = if card_set.awaits_typed_answer() {
+                TYPE_HINTS_TEXT.to_string()
+            } else if card_set.awaits_choice() {
+                let pick = format!("1-{}:pick answer", card_data::CHOICES);
+                hints_text(&keymap, HINTS, vec![pick])
+            } else if card_set.is_reversed() {
+                hints_text(&keymap, &[GRADE_HINTS, HINTS].concat(), vec![])
+            } else {
+                hints_text(&keymap, HINTS, vec![])
+            };
+            let hints = match count {
+                Some(count) => format!("{} {}", count, hints),
+                None => hints,
+            };
+            view.get_inner_mut().set_content(hints);
             view.set_height(SizeConstraint::Fixed(1));
         } else {
             view.set_height(SizeConstraint::Fixed(0));
@@ -129,6 +234,242 @@ pub fn display(siv: &mut Cursive, card_set: &CardSet) {
     }
 }
 
+// Shortcuts without keys are left out
+fn hints_text(keymap: &Keymap, shortcuts: &[(Shortcut, &str)], mut hints: Vec<String>) -> String {
+    shortcuts.iter().for_each(|(shortcut, desc)| {
+        if let Some(key) = keymap.hint(*shortcut) {
+            hints.push(format!("{}:{}", key, desc));
+        }
+    });
+    format!("| {} |", hints.join(" | "))
+}
+
+fn highlight_term(text: &str, term: Option<String>) -> StyledString {
+    let position = term
+        .filter(|term| !term.is_empty())
+        .and_then(|term| text.find(&term).map(|start| (start, start + term.len())));
+    match position {
+        Some((start, end)) => {
+            let mut styled = StyledString::plain(&text[..start]);
+            styled.append_styled(
+                &text[start..end],
+                Style::from(CORRECT_COLOR).combine(Effect::Underline),
+            );
+            styled.append_plain(&text[end..]);
+            styled
+        }
+        None => StyledString::plain(text),
+    }
+}
+
+fn update_typed_answer(siv: &mut Cursive, card_set: &CardSet) {
+    if card_set.get_mode() != Mode::Type {
+        return;
+    }
+
+    if let Some(mut view) = siv.find_name::<EditView>(ANSWER) {
+        if !card_set.awaits_typed_answer() {
+            view.disable();
+        } else if !view.is_enabled() {
+            view.set_content("");
+            view.enable();
+        }
+    }
+    if card_set.awaits_typed_answer() {
+        siv.focus_name(ANSWER).ok();
+    }
+
+    if let Some(mut view) = siv.find_name::<ResizedView<TextView>>(ANSWER_RESULT) {
+        let result = match (card_set.get_typed_answer(), card_set.get_expected_answer()) {
+            (Some((answer, result)), Some(expected)) => {
+                let mut text = StyledString::new();
+                answer::diff(expected, answer)
+                    .into_iter()
+                    .for_each(|c| match c {
+                        DiffChar::Ok(c) => text.append_plain(c.to_string()),
+                        DiffChar::Wrong(c) => text.append_styled(c.to_string(), WRONG_COLOR),
+                        DiffChar::Missing(_) => text.append_styled("_", WRONG_COLOR),
+                    });
+                let verdict_color = if result.is_correct() {
+                    CORRECT_COLOR
+                } else {
+                    WRONG_COLOR
+                };
+                text.append_plain("\n");
+                text.append_styled(result.desc(), verdict_color);
+                text
+            }
+            _ => StyledString::new(),
+        };
+        view.get_inner_mut().set_content(result);
+    }
+}
+
+fn update_choices(siv: &mut Cursive, card_set: &CardSet) {
+    if let Some(mut view) = siv.find_name::<TextView>(CHOICES) {
+        let mut text = StyledString::new();
+        card_set
+            .get_choices()
+            .iter()
+            .enumerate()
+            .for_each(|(nr, choice)| {
+                let line = format!("{}. {}\n", nr + 1, choice.text);
+                if card_set.awaits_choice() {
+                    text.append_plain(line);
+                } else if choice.correct {
+                    text.append_styled(line, CORRECT_COLOR);
+                } else if choice.chosen {
+                    text.append_styled(line, WRONG_COLOR);
+                } else {
+                    text.append_plain(line);
+                }
+            });
+        view.set_content(text);
+    }
+}
+
+pub fn show_end_of_round(siv: &mut Cursive, card_set: &CardSet) {
+    if siv.find_name::<Dialog>(END_OF_ROUND_DIALOG).is_some() {
+        return;
+    }
+
+    let to_repeat = card_set.cards_to_repeat();
+    let mut text = format!("Round {} finished\n\n", card_set.round_nr());
+    if to_repeat > 0 {
+        text.push_str(&format!(
+            "{} of {} cards marked to be repeated",
+            to_repeat,
+            card_set.cards_len()
+        ));
+    } else {
+        text.push_str("No cards marked to be repeated");
+    }
+    if let Some(answers) = Answers::of(card_set) {
+        text.push_str(&format!("\nAnswers so far: {}", answers.text()));
+    }
+
+    let dialog = if to_repeat > 0 {
+        Dialog::text(text).button("Next round", |s| {
+            s.pop_layer();
+            card_logic::start_next_round(s);
+        })
+    } else {
+        Dialog::text(text).button("Quit", |s| {
+            s.pop_layer();
+            card_logic::quit(s);
+        })
+    };
+
+    siv.add_layer(
+        dialog
+            .title("End of round")
+            .dismiss_button("Back")
+            .with_name(END_OF_ROUND_DIALOG),
+    );
+}
+
+pub fn show_summary(siv: &mut Cursive, summary: &Summary) {
+    siv.add_layer(
+        Dialog::text(summary.text())
+            .title("Session summary")
+            .button("Quit", |s| s.quit())
+            .dismiss_button("Back")
+            .with_name(SUMMARY_DIALOG),
+    );
+}
+
+pub fn show_go_to_card(siv: &mut Cursive, card_set: &CardSet) {
+    if card_set.cards_len() == 0 {
+        return;
+    }
+
+    let edit = EditView::new().on_submit(|s, text| match text.trim().parse::<usize>() {
+        Ok(card_nr) => {
+            s.pop_layer();
+            card_logic::go_to(s, card_nr);
+        }
+        Err(_) => s.add_layer(Dialog::info(format!("Invalid card number: {}", text))),
+    });
+    siv.add_layer(
+        Dialog::around(edit.fixed_width(GO_TO_CARD_WIDTH))
+            .title(format!("Go to card (1-{})", card_set.cards_len()))
+            .dismiss_button("Cancel")
+            .with_name(GO_TO_CARD_DIALOG),
+    );
+}
+
+pub fn show_filter(siv: &mut Cursive, card_set: &CardSet) {
+    let edit = EditView::new()
+        .content(card_set.get_filter().to_string())
+        .on_submit(|s, text| match text.parse() {
+            Ok(filter) => {
+                s.pop_layer();
+                card_logic::set_filter(s, filter);
+            }
+            Err(e) => s.add_layer(Dialog::info(format!("Invalid filter: {}", e))),
+        });
+    siv.add_layer(
+        Dialog::around(
+            LinearLayout::vertical()
+                .child(edit.fixed_width(FILTER_WIDTH))
+                .child(TextView::new(FILTER_HELP)),
+        )
+        .title("Filter cards")
+        .dismiss_button("Cancel")
+        .with_name(FILTER_DIALOG),
+    );
+}
+
+pub fn show_search(siv: &mut Cursive) {
+    let query = EditView::new()
+        .on_edit(|s, text, _| update_search_results(s, text))
+        .on_submit(|s, _| {
+            let selected = s
+                .find_name::<SelectView<usize>>(SEARCH_RESULTS)
+                .and_then(|view| view.selection());
+            if let Some(idx) = selected {
+                submit_search(s, *idx);
+            }
+        })
+        .with_name(SEARCH_QUERY);
+    let results = SelectView::<usize>::new()
+        .on_submit(|s, idx: &usize| submit_search(s, *idx))
+        .with_name(SEARCH_RESULTS)
+        .scrollable()
+        .fixed_size((SEARCH_WIDTH, SEARCH_HEIGHT));
+
+    siv.add_layer(
+        Dialog::around(LinearLayout::vertical().child(query).child(results))
+            .title("Search")
+            .dismiss_button("Cancel")
+            .with_name(SEARCH_DIALOG),
+    );
+}
+
+fn update_search_results(siv: &mut Cursive, query: &str) {
+    let results = card_logic::search_results(siv, query);
+    if let Some(mut dialog) = siv.find_name::<Dialog>(SEARCH_DIALOG) {
+        match results.len() {
+            0 => dialog.set_title("Search"),
+            1 => dialog.set_title("Search: 1 match"),
+            n => dialog.set_title(format!("Search: {} matches", n)),
+        }
+    }
+    if let Some(mut view) = siv.find_name::<SelectView<usize>>(SEARCH_RESULTS) {
+        view.clear();
+        view.add_all(results);
+    }
+}
+
+fn submit_search(siv: &mut Cursive, idx: usize) {
+    let query = siv
+        .find_name::<EditView>(SEARCH_QUERY)
+        .map(|view| view.get_content().to_string())
+        .unwrap_or_default();
+    siv.pop_layer();
+    card_logic::jump_to_match(siv, &query, idx);
+}
+
 fn update_weight(siv: &mut Cursive, val: i32, name: &str) {
     if let Some(mut view) = siv.find_name::<ResizedView<TextView>>(name) {
         if val > 0 {
@@ -149,7 +490,7 @@ where
                 view.set_height(SizeConstraint::Fixed(0));
             }
             Some(text) => {
-                cb(&mut view, *text);
+                cb(&mut view, text);
             }
         }
     }
@@ -0,0 +1,181 @@
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::card::card_data::CardData;
+
+const TYPE_PREFIX: &str = "type:";
+const DECK_PREFIX: &str = "deck:";
+const EXCLUDE_PREFIX: &str = "-";
+
+// Cards with any of `tags`, none of `exclude_tags`, of any of `types` and from any of `decks`;
+// an empty list does not narrow the cards
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Filter {
+    pub tags: Vec<String>,
+    pub exclude_tags: Vec<String>,
+    pub types: Vec<String>,
+    pub decks: Vec<String>,
+}
+
+impl Filter {
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+            && self.exclude_tags.is_empty()
+            && self.types.is_empty()
+            && self.decks.is_empty()
+    }
+
+    pub fn matches(&self, card: &CardData) -> bool {
+        let has_any_tag = |tags: &[String]| tags.iter().any(|tag| card.has_tag(tag));
+        let has_type = || {
+            card.card_type.as_deref().is_some_and(|card_type| {
+                self.types
+                    .iter()
+                    .any(|wanted| wanted.trim().eq_ignore_ascii_case(card_type.trim()))
+            })
+        };
+        let from_deck = || {
+            card.source
+                .as_deref()
+                .is_some_and(|source| self.decks.iter().any(|deck| is_deck(source, deck)))
+        };
+        (self.tags.is_empty() || has_any_tag(&self.tags))
+            && !has_any_tag(&self.exclude_tags)
+            && (self.types.is_empty() || has_type())
+            && (self.decks.is_empty() || from_deck())
+    }
+}
+
+// A deck is given by its path, file name or file name without extension
+fn is_deck(source: &Path, deck: &str) -> bool {
+    let deck = deck.trim();
+    let named = |name: Option<&std::ffi::OsStr>| {
+        name.is_some_and(|name| name.to_string_lossy().eq_ignore_ascii_case(deck))
+    };
+    source == Path::new(deck) || named(source.file_name()) || named(source.file_stem())
+}
+
+// Written like "animals -archaic type:noun deck:verbs"
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut filter = Filter::default();
+        for term in s.split_whitespace() {
+            let (list, value) = if let Some(card_type) = term.strip_prefix(TYPE_PREFIX) {
+                (&mut filter.types, card_type)
+            } else if let Some(deck) = term.strip_prefix(DECK_PREFIX) {
+                (&mut filter.decks, deck)
+            } else if let Some(tag) = term.strip_prefix(EXCLUDE_PREFIX) {
+                (&mut filter.exclude_tags, tag)
+            } else {
+                (&mut filter.tags, term)
+            };
+            if value.is_empty() {
+                return Err(format!("'{}' has no value", term));
+            }
+            list.push(value.to_string());
+        }
+        Ok(filter)
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let terms: Vec<String> = self
+            .tags
+            .iter()
+            .map(String::clone)
+            .chain(
+                self.exclude_tags
+                    .iter()
+                    .map(|tag| format!("{}{}", EXCLUDE_PREFIX, tag)),
+            )
+            .chain(
+                self.types
+                    .iter()
+                    .map(|card_type| format!("{}{}", TYPE_PREFIX, card_type)),
+            )
+            .chain(
+                self.decks
+                    .iter()
+                    .map(|deck| format!("{}{}", DECK_PREFIX, deck)),
+            )
+            .collect();
+        f.write_str(&terms.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn card(card_type: Option<&str>, tags: &[&str]) -> CardData {
+        CardData {
+            word: "kot".to_string(),
+            translated: "cat".to_string(),
+            card_type: card_type.map(String::from),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            source: Some(PathBuf::from("decks/nouns.json")),
+            ..CardData::default()
+        }
+    }
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn cards_are_filtered_by_tags_and_type() {
+        let filter = Filter {
+            tags: strings(&["animals", "week3"]),
+            exclude_tags: strings(&["archaic"]),
+            types: strings(&["noun"]),
+            decks: vec![],
+        };
+
+        assert!(filter.matches(&card(Some("Noun"), &["Animals"])));
+        assert!(!filter.matches(&card(Some("noun"), &["animals", "archaic"])));
+        assert!(!filter.matches(&card(Some("verb"), &["week3"])));
+        assert!(!filter.matches(&card(None, &["week3"])));
+        assert!(!filter.matches(&card(Some("noun"), &[])));
+        assert!(Filter::default().matches(&card(None, &[])));
+    }
+
+    #[test]
+    fn cards_are_filtered_by_deck() {
+        let deck = |deck: &str| Filter {
+            decks: strings(&[deck]),
+            ..Filter::default()
+        };
+
+        assert!(deck("nouns").matches(&card(None, &[])));
+        assert!(deck("Nouns.json").matches(&card(None, &[])));
+        assert!(deck("decks/nouns.json").matches(&card(None, &[])));
+        assert!(!deck("verbs").matches(&card(None, &[])));
+        assert!(!deck("nouns").matches(&CardData::default()));
+    }
+
+    #[test]
+    fn filter_is_written_as_text() {
+        let filter: Filter = " animals -archaic type:noun  deck:verbs week3"
+            .parse()
+            .unwrap();
+
+        assert_eq!(strings(&["animals", "week3"]), filter.tags);
+        assert_eq!(strings(&["archaic"]), filter.exclude_tags);
+        assert_eq!(strings(&["noun"]), filter.types);
+        assert_eq!(strings(&["verbs"]), filter.decks);
+        assert_eq!(
+            "animals week3 -archaic type:noun deck:verbs",
+            filter.to_string()
+        );
+        assert!("".parse::<Filter>().unwrap().is_empty());
+        assert_eq!(
+            Err("'type:' has no value".to_string()),
+            "type:".parse::<Filter>()
+        );
+    }
+}
@@ -1,3 +1,6 @@
 pub mod card_data;
+#[cfg(feature = "tui")]
 pub mod card_logic;
+#[cfg(feature = "tui")]
 pub mod card_ui;
+pub mod filter;
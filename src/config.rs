@@ -0,0 +1,236 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use cursive::theme::{Color, PaletteColor, Theme};
+use serde::Deserialize;
+
+use crate::card::card_data::{Mode, Visibility};
+use crate::shortcuts::Keymap;
+use crate::xdg;
+
+const CONFIG_FILE: &str = "config.toml";
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    shuffle: Option<bool>,
+    mode: Option<String>,
+    visibility: Option<Visibility>,
+    keys: BTreeMap<String, Keys>,
+    theme: ThemeFile,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum Keys {
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+struct ThemeFile {
+    shadow: bool,
+    palette: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Default)]
+pub struct Config {
+    pub shuffle: Option<bool>,
+    pub mode: Option<Mode>,
+    pub visibility: Visibility,
+    pub keymap: Keymap,
+    shadow: bool,
+    palette: Vec<(PaletteColor, Color)>,
+}
+
+impl Config {
+    // Without `path` the file is looked up in the XDG config directory and may be missing
+    pub fn load(path: Option<&str>) -> Result<Self, Box<dyn Error>> {
+        let (path, required) = match path {
+            Some(path) => (PathBuf::from(path), true),
+            None => match xdg::config_dir() {
+                Some(dir) => (dir.join(CONFIG_FILE), false),
+                None => return Ok(Config::default()),
+            },
+        };
+        match fs::read_to_string(&path) {
+            Ok(content) => Self::parse(&content)
+                .map_err(|e| format!("Invalid config {}: {}", path.display(), e).into()),
+            Err(e) if e.kind() == ErrorKind::NotFound && !required => Ok(Config::default()),
+            Err(e) => Err(format!("Cannot read config {}: {}", path.display(), e).into()),
+        }
+    }
+
+    fn parse(content: &str) -> Result<Self, Box<dyn Error>> {
+        let file: ConfigFile = toml::from_str(content)?;
+
+        let mode = match file.mode {
+            Some(mode) => Some(mode.parse()?),
+            None => None,
+        };
+        let keys: Vec<(&str, Vec<String>)> = file
+            .keys
+            .iter()
+            .map(|(name, keys)| match keys {
+                Keys::One(key) => (name.as_str(), vec![key.clone()]),
+                Keys::Many(keys) => (name.as_str(), keys.clone()),
+            })
+            .collect();
+        let keymap =
+            Keymap::with_bindings(keys.iter().map(|(name, keys)| (*name, keys.as_slice())))?;
+        let palette = file
+            .theme
+            .palette
+            .iter()
+            .map(|(name, color)| {
+                let name = name
+                    .parse::<PaletteColor>()
+                    .map_err(|_| format!("unknown palette color '{}'", name))?;
+                let color =
+                    Color::parse(color).ok_or_else(|| format!("invalid color '{}'", color))?;
+                Ok((name, color))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(Config {
+            shuffle: file.shuffle,
+            mode,
+            visibility: file.visibility.unwrap_or_default(),
+            keymap,
+            shadow: file.theme.shadow,
+            palette,
+        })
+    }
+
+    pub fn apply_theme(&self, theme: &mut Theme) {
+        theme.shadow = self.shadow;
+        self.palette
+            .iter()
+            .for_each(|(name, color)| theme.palette[*name] = *color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cursive::event::{Event, Key};
+    use cursive::theme::BaseColor;
+
+    use super::*;
+    use crate::shortcuts::Shortcut;
+
+    #[test]
+    fn empty_config_keeps_defaults() {
+        let config = Config::parse("").unwrap();
+
+        assert_eq!(None, config.shuffle);
+        assert_eq!(None, config.mode);
+        assert_eq!(Visibility::default(), config.visibility);
+        assert_eq!(&[Event::Char('q')], config.keymap.events(Shortcut::Quit));
+    }
+
+    #[test]
+    fn full_config() {
+        let config = Config::parse(
+            r##"
+            shuffle = true
+            mode = "type"
+
+            [visibility]
+            show_example = true
+            show_hints = false
+
+            [keys]
+            next_card = ["j", "right"]
+            prev_card = "k"
+
+            [theme]
+            shadow = true
+            palette = { background = "default", primary = "light green", view = "#102030" }
+            "##,
+        )
+        .unwrap();
+        let mut theme = Theme::default();
+        config.apply_theme(&mut theme);
+
+        assert_eq!(Some(true), config.shuffle);
+        assert_eq!(Some(Mode::Type), config.mode);
+        assert!(config.visibility.show_example);
+        assert!(!config.visibility.show_hints);
+        assert!(config.visibility.show_title);
+        assert_eq!(
+            &[Event::Char('j'), Event::Key(Key::Right)],
+            config.keymap.events(Shortcut::NextCard)
+        );
+        assert_eq!(
+            &[Event::Char('k')],
+            config.keymap.events(Shortcut::PrevCard)
+        );
+        assert!(theme.shadow);
+        assert_eq!(
+            Color::TerminalDefault,
+            theme.palette[PaletteColor::Background]
+        );
+        assert_eq!(
+            Color::Light(BaseColor::Green),
+            theme.palette[PaletteColor::Primary]
+        );
+        assert_eq!(
+            Color::Rgb(0x10, 0x20, 0x30),
+            theme.palette[PaletteColor::View]
+        );
+    }
+
+    #[test]
+    fn unknown_settings_are_reported() {
+        let error = Config::parse("shufle = true").unwrap_err().to_string();
+        assert!(error.starts_with("unknown field `shufle`"), "{}", error);
+
+        let error = Config::parse("[visibility]\nshow_everything = true")
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.starts_with("unknown field `show_everything`"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn invalid_values_are_reported() {
+        assert_eq!(
+            "Unknown mode: typing",
+            Config::parse("mode = \"typing\"").unwrap_err().to_string()
+        );
+        assert_eq!(
+            "invalid key 'ctrl-q' for quit",
+            Config::parse("[keys]\nquit = \"ctrl-q\"")
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(
+            "invalid color 'greenish'",
+            Config::parse("[theme.palette]\nprimary = \"greenish\"")
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(
+            "unknown palette color 'text'",
+            Config::parse("[theme.palette]\ntext = \"green\"")
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn missing_config_file_given_explicitly_is_an_error() {
+        let error = Config::load(Some("/nonexistent/config.toml"))
+            .unwrap_err()
+            .to_string();
+
+        assert!(error.starts_with("Cannot read config /nonexistent/config.toml"));
+    }
+}
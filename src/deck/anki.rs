@@ -0,0 +1,586 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use rusqlite::{Connection, OpenFlags};
+use serde::Deserialize;
+use tempfile::NamedTempFile;
+use zip::ZipArchive;
+
+use crate::card::card_data::CardData;
+use crate::deck::delimited::Column;
+use crate::error::Error;
+
+// Newer Anki versions put a dummy "please update" collection into collection.anki2
+const COLLECTIONS: [&str; 2] = ["collection.anki21", "collection.anki2"];
+const COMPRESSED_COLLECTION: &str = "collection.anki21b";
+const FIELD_SEPARATOR: char = '\x1f';
+
+#[derive(Debug, Default)]
+pub struct Import {
+    pub cards: Vec<CardData>,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug)]
+struct NoteType {
+    name: String,
+    fields: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Model {
+    name: String,
+    flds: Vec<ModelField>,
+}
+
+#[derive(Deserialize)]
+struct ModelField {
+    name: String,
+    ord: usize,
+}
+
+// `mappings` maps note type names to card fields of the note fields, in field order.
+// Note types without a mapping use field names, e.g. Front/Back, falling back to
+// the first two fields as word and translation.
+pub fn import<P: AsRef<Path>>(
+    path: P,
+    mappings: &HashMap<String, Vec<String>>,
+) -> Result<Import, Error> {
+    let path = path.as_ref();
+    read_package(path, mappings)
+        .map_err(|e| format!("Cannot import {}: {}", path.display(), e).into())
+}
+
+fn read_package(path: &Path, mappings: &HashMap<String, Vec<String>>) -> Result<Import, Error> {
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+    let collection = extract_collection(&mut archive)?;
+    let connection =
+        Connection::open_with_flags(collection.path(), OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    read_collection(&connection, mappings)
+}
+
+fn extract_collection(archive: &mut ZipArchive<File>) -> Result<NamedTempFile, Error> {
+    let name = COLLECTIONS
+        .iter()
+        .find(|name| archive.by_name(name).is_ok())
+        .ok_or_else(|| {
+            if archive.by_name(COMPRESSED_COLLECTION).is_ok() {
+                "compressed collections are not supported; export with \"Support older Anki versions\" checked"
+            } else {
+                "no Anki collection in the package"
+            }
+        })?;
+    let mut collection = NamedTempFile::new()?;
+    io::copy(&mut archive.by_name(name)?, &mut collection)?;
+    Ok(collection)
+}
+
+fn read_collection(
+    connection: &Connection,
+    mappings: &HashMap<String, Vec<String>>,
+) -> Result<Import, Error> {
+    let note_types = read_note_types(connection)?;
+    let mut import = Import::default();
+    mappings
+        .keys()
+        .filter(|name| {
+            !note_types
+                .values()
+                .any(|note_type| &note_type.name == *name)
+        })
+        .for_each(|name| {
+            import
+                .warnings
+                .push(format!("note type '{}' is not in the collection", name))
+        });
+    let columns = note_types
+        .iter()
+        .map(|(id, note_type)| {
+            Ok((
+                *id,
+                field_columns(note_type, mappings.get(&note_type.name))?,
+            ))
+        })
+        .collect::<Result<HashMap<i64, Vec<Column>>, Error>>()?;
+
+    let mut statement = connection.prepare("SELECT id, mid, flds FROM notes ORDER BY id")?;
+    let notes = statement.query_map(rusqlite::NO_PARAMS, |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, i64>(1)?,
+            row.get::<_, String>(2)?,
+        ))
+    })?;
+    for note in notes {
+        let (id, note_type_id, fields) = note?;
+        let (note_type, columns) = match (note_types.get(&note_type_id), columns.get(&note_type_id))
+        {
+            (Some(note_type), Some(columns)) => (note_type, columns),
+            _ => {
+                import
+                    .warnings
+                    .push(format!("note {}: unknown note type, skipped", id));
+                continue;
+            }
+        };
+
+        let mut card = CardData::default();
+        let mut media = vec![];
+        columns
+            .iter()
+            .zip(fields.split(FIELD_SEPARATOR))
+            .for_each(|(column, value)| {
+                let (text, references) = plain_text(value);
+                if *column != Column::Skip {
+                    media.extend(references);
+                }
+                column.set(&mut card, &text);
+            });
+
+        if !media.is_empty() {
+            import.warnings.push(format!(
+                "note {} ({}): media not imported: {}",
+                id,
+                note_type.name,
+                media.join(", ")
+            ));
+        }
+        if card.word.is_empty() || card.translated.is_empty() {
+            import.warnings.push(format!(
+                "note {} ({}): empty word or translation, skipped",
+                id, note_type.name
+            ));
+        } else {
+            import.cards.push(card);
+        }
+    }
+    Ok(import)
+}
+
+fn read_note_types(connection: &Connection) -> Result<HashMap<i64, NoteType>, Error> {
+    let models: String =
+        connection.query_row("SELECT models FROM col", rusqlite::NO_PARAMS, |row| {
+            row.get(0)
+        })?;
+    if models.trim().is_empty() || models.trim() == "{}" {
+        // collections since Anki 2.1.28 keep note types in separate tables
+        return read_note_type_tables(connection);
+    }
+
+    let models: HashMap<String, Model> = serde_json::from_str(&models)?;
+    models
+        .into_iter()
+        .map(|(id, mut model)| {
+            model.flds.sort_by_key(|field| field.ord);
+            let note_type = NoteType {
+                name: model.name,
+                fields: model.flds.into_iter().map(|field| field.name).collect(),
+            };
+            let id = id
+                .parse()
+                .map_err(|_| format!("Invalid note type id '{}'", id))?;
+            Ok((id, note_type))
+        })
+        .collect()
+}
+
+fn read_note_type_tables(connection: &Connection) -> Result<HashMap<i64, NoteType>, Error> {
+    let mut note_types = HashMap::new();
+    let mut statement = connection.prepare("SELECT id, name FROM notetypes")?;
+    for row in statement.query_map(rusqlite::NO_PARAMS, |row| Ok((row.get(0)?, row.get(1)?)))? {
+        let (id, name) = row?;
+        note_types.insert(
+            id,
+            NoteType {
+                name,
+                fields: vec![],
+            },
+        );
+    }
+
+    let mut statement = connection.prepare("SELECT ntid, name FROM fields ORDER BY ntid, ord")?;
+    for row in statement.query_map(rusqlite::NO_PARAMS, |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+    })? {
+        let (id, name) = row?;
+        if let Some(note_type) = note_types.get_mut(&id) {
+            note_type.fields.push(name);
+        }
+    }
+    Ok(note_types)
+}
+
+fn field_columns(
+    note_type: &NoteType,
+    mapping: Option<&Vec<String>>,
+) -> Result<Vec<Column>, Error> {
+    match mapping {
+        Some(mapping) => {
+            let mut columns = mapping
+                .iter()
+                .map(|name| {
+                    Column::parse(name).ok_or_else(|| {
+                        format!("note type '{}': unknown column '{}'", note_type.name, name)
+                    })
+                })
+                .collect::<Result<Vec<Column>, String>>()?;
+            if !columns.contains(&Column::Word) || !columns.contains(&Column::Translated) {
+                return Err(format!(
+                    "note type '{}': columns must include at least word and translated",
+                    note_type.name
+                )
+                .into());
+            }
+            columns.resize(note_type.fields.len().max(columns.len()), Column::Skip);
+            Ok(columns)
+        }
+        None => Ok(default_columns(&note_type.fields)),
+    }
+}
+
+fn default_columns(fields: &[String]) -> Vec<Column> {
+    let mut columns: Vec<Column> = vec![];
+    fields.iter().for_each(|field| {
+        let column = match field.trim().to_lowercase().as_str() {
+            "front" | "text" => Some(Column::Word),
+            "back" => Some(Column::Translated),
+            "back extra" | "extra" => Some(Column::Explanation),
+            name => Column::parse(name),
+        }
+        .filter(|column| !columns.contains(column))
+        .unwrap_or(Column::Skip);
+        columns.push(column);
+    });
+
+    [Column::Word, Column::Translated]
+        .iter()
+        .for_each(|required| {
+            if !columns.contains(required) {
+                if let Some(column) = columns.iter_mut().find(|column| **column == Column::Skip) {
+                    *column = *required;
+                }
+            }
+        });
+    columns
+}
+
+// Returns the text without HTML markup, sound tags and cloze markers, and the media it referred to
+fn plain_text(html: &str) -> (String, Vec<String>) {
+    let mut text = String::new();
+    let mut media = vec![];
+    let mut chars = html.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '<' => {
+                let tag: String = chars.by_ref().take_while(|c| *c != '>').collect();
+                let name = tag
+                    .trim_start_matches('/')
+                    .split(|c: char| c.is_whitespace() || c == '/')
+                    .next()
+                    .unwrap_or_default()
+                    .to_lowercase();
+                match name.as_str() {
+                    "img" | "audio" | "video" | "source" => media.extend(attribute(&tag, "src")),
+                    "br" | "div" | "p" | "li" | "tr" | "td" => text.push(' '),
+                    _ => {}
+                }
+            }
+            '&' => {
+                let mut entity = String::new();
+                while let Some(c) = chars.peek() {
+                    if *c == ';' || entity.len() > 8 || !(c.is_alphanumeric() || *c == '#') {
+                        break;
+                    }
+                    entity.push(*c);
+                    chars.next();
+                }
+                match (chars.peek(), decode_entity(&entity)) {
+                    (Some(';'), Some(decoded)) => {
+                        chars.next();
+                        text.push(decoded);
+                    }
+                    _ => {
+                        text.push('&');
+                        text.push_str(&entity);
+                    }
+                }
+            }
+            c => text.push(c),
+        }
+    }
+
+    let text = extract_sounds(&text, &mut media);
+    let text = unwrap_clozes(&text);
+    (text.split_whitespace().collect::<Vec<_>>().join(" "), media)
+}
+
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let pattern = format!("{}=", name);
+    let start = tag
+        .as_bytes()
+        .windows(pattern.len())
+        .position(|window| window.eq_ignore_ascii_case(pattern.as_bytes()))?
+        + pattern.len();
+    let value = &tag[start..];
+    let value = match value.chars().next()? {
+        quote @ '"' | quote @ '\'' => value[1..].split(quote).next()?,
+        _ => value
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()?,
+    };
+    Some(value.to_string()).filter(|value| !value.is_empty())
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    match entity {
+        "nbsp" => Some(' '),
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        _ => {
+            let code = entity.strip_prefix('#')?;
+            let code = match code.strip_prefix('x').or_else(|| code.strip_prefix('X')) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => code.parse().ok()?,
+            };
+            std::char::from_u32(code)
+        }
+    }
+}
+
+fn extract_sounds(text: &str, media: &mut Vec<String>) -> String {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("[sound:") {
+        match rest[start..].find(']') {
+            Some(end) => {
+                result.push_str(&rest[..start]);
+                media.push(rest[start + "[sound:".len()..start + end].to_string());
+                rest = &rest[start + end + 1..];
+            }
+            None => break,
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+// {{c1::answer::hint}} becomes "answer"
+fn unwrap_clozes(text: &str) -> String {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{c") {
+        result.push_str(&rest[..start]);
+        let cloze = &rest[start..];
+        let answer = cloze.find("}}").and_then(|end| {
+            let (number, content) = cloze[3..end].split_once("::")?;
+            if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit()) {
+                return None;
+            }
+            Some((content.split("::").next().unwrap_or_default(), end + 2))
+        });
+        match answer {
+            Some((answer, len)) => {
+                result.push_str(answer);
+                rest = &cloze[len..];
+            }
+            None => {
+                result.push_str("{{c");
+                rest = &cloze[3..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    use super::*;
+
+    const MODELS: &str = r#"{
+        "1": {"name": "Basic", "flds": [{"name": "Back", "ord": 1}, {"name": "Front", "ord": 0}]},
+        "2": {"name": "Vocabulary", "flds": [{"name": "Polish", "ord": 0}, {"name": "English", "ord": 1}, {"name": "Notes", "ord": 2}]}
+    }"#;
+
+    fn package(collection_name: &str, notes: &[(i64, i64, &str)]) -> NamedTempFile {
+        let collection = NamedTempFile::new().unwrap();
+        let connection = Connection::open(collection.path()).unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE col (models TEXT NOT NULL);
+                 CREATE TABLE notes (id INTEGER PRIMARY KEY, mid INTEGER NOT NULL, flds TEXT NOT NULL);",
+            )
+            .unwrap();
+        connection
+            .execute("INSERT INTO col (models) VALUES (?)", &[MODELS])
+            .unwrap();
+        notes.iter().for_each(|(id, mid, flds)| {
+            connection
+                .execute(
+                    "INSERT INTO notes (id, mid, flds) VALUES (?, ?, ?)",
+                    rusqlite::params![id, mid, flds],
+                )
+                .unwrap();
+        });
+        drop(connection);
+
+        let package = NamedTempFile::new().unwrap();
+        let mut zip = ZipWriter::new(File::create(package.path()).unwrap());
+        zip.start_file(collection_name, FileOptions::default())
+            .unwrap();
+        zip.write_all(&std::fs::read(collection.path()).unwrap())
+            .unwrap();
+        zip.start_file("media", FileOptions::default()).unwrap();
+        zip.write_all(b"{}").unwrap();
+        zip.finish().unwrap();
+        package
+    }
+
+    #[test]
+    fn fields_are_matched_by_name() {
+        let package = package(
+            "collection.anki21",
+            &[
+                (10, 1, "kot\u{1f}<b>cat</b>"),
+                (11, 2, "pies\u{1f}dog\u{1f}a pet"),
+            ],
+        );
+
+        let import = import(package.path(), &HashMap::new()).unwrap();
+
+        assert_eq!(2, import.cards.len());
+        assert_eq!("kot", import.cards[0].word);
+        assert_eq!("cat", import.cards[0].translated);
+        assert_eq!("pies", import.cards[1].word);
+        assert_eq!("dog", import.cards[1].translated);
+        assert_eq!("", import.cards[1].explanation);
+        assert!(import.warnings.is_empty());
+    }
+
+    #[test]
+    fn mapping_is_configurable_per_note_type() {
+        let package = package("collection.anki2", &[(10, 2, "pies\u{1f}dog\u{1f}a pet")]);
+        let mut mappings = HashMap::new();
+        mappings.insert(
+            "Vocabulary".to_string(),
+            vec![
+                "translated".to_string(),
+                "word".to_string(),
+                "explanation".to_string(),
+            ],
+        );
+
+        let import = import(package.path(), &mappings).unwrap();
+
+        assert_eq!("dog", import.cards[0].word);
+        assert_eq!("pies", import.cards[0].translated);
+        assert_eq!("a pet", import.cards[0].explanation);
+    }
+
+    #[test]
+    fn media_references_are_reported() {
+        let package = package(
+            "collection.anki21",
+            &[
+                (10, 1, "kot [sound:kot.mp3]\u{1f}cat<img src=\"cat.jpg\">"),
+                (11, 1, "<img src='x.png'>\u{1f}"),
+            ],
+        );
+
+        let import = import(package.path(), &HashMap::new()).unwrap();
+
+        assert_eq!(1, import.cards.len());
+        assert_eq!("kot", import.cards[0].word);
+        assert_eq!(
+            vec![
+                "note 10 (Basic): media not imported: kot.mp3, cat.jpg",
+                "note 11 (Basic): media not imported: x.png",
+                "note 11 (Basic): empty word or translation, skipped",
+            ],
+            import.warnings
+        );
+    }
+
+    #[test]
+    fn imported_cards_can_be_read_back() {
+        let package = package("collection.anki21", &[(10, 1, "kot\u{1f}cat")]);
+        let import = import(package.path(), &HashMap::new()).unwrap();
+
+        let json = serde_json::to_string_pretty(&import.cards).unwrap();
+        let cards: Vec<CardData> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(import.cards, cards);
+    }
+
+    #[test]
+    fn compressed_collection_is_reported() {
+        let package = package("collection.anki21b", &[]);
+
+        let error = import(package.path(), &HashMap::new())
+            .unwrap_err()
+            .to_string();
+
+        assert!(error.contains("compressed collections are not supported"));
+    }
+
+    #[test]
+    fn unknown_mapping_column_is_reported() {
+        let package = package("collection.anki21", &[]);
+        let mut mappings = HashMap::new();
+        mappings.insert("Basic".to_string(), vec!["front".to_string()]);
+
+        let error = import(package.path(), &mappings).unwrap_err().to_string();
+
+        assert!(error.ends_with("note type 'Basic': unknown column 'front'"));
+    }
+
+    #[test]
+    fn html_is_stripped() {
+        assert_eq!(
+            ("a b & <c> \u{e9}".to_string(), vec![]),
+            plain_text("<div>a</div><br/>b &amp; &lt;c&gt;&nbsp;&#233;")
+        );
+        assert_eq!(("AT&T".to_string(), vec![]), plain_text("AT&T"));
+    }
+
+    #[test]
+    fn attributes_are_found_next_to_non_ascii_text() {
+        assert_eq!(
+            Some("kot.png".to_string()),
+            attribute("img alt=\"\u{130}\u{130}\u{130}\" SRC=\"kot.png\"", "src")
+        );
+        assert_eq!(None, attribute("img alt=\"\u{130}src\"", "src"));
+    }
+
+    #[test]
+    fn cloze_markers_are_unwrapped() {
+        assert_eq!(
+            "The cat sat on the mat",
+            plain_text("The {{c1::cat::animal}} sat on the {{c2::mat}}").0
+        );
+    }
+
+    #[test]
+    fn default_columns_fall_back_to_first_fields() {
+        let fields = vec![
+            "Polish".to_string(),
+            "Sentence".to_string(),
+            "English".to_string(),
+        ];
+
+        assert_eq!(
+            vec![Column::Word, Column::Sentence, Column::Translated],
+            default_columns(&fields)
+        );
+    }
+}
@@ -0,0 +1,311 @@
+use csv::{ReaderBuilder, StringRecord};
+
+use crate::card::card_data::{split_tags, CardData};
+use crate::error::{DeckError, Diagnostic};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(super) enum Column {
+    Word,
+    Translated,
+    Explanation,
+    Pronunciation,
+    Sentence,
+    Type,
+    SentenceGap,
+    GapTerm,
+    Tags,
+    Skip,
+}
+
+impl Column {
+    pub(super) fn parse(name: &str) -> Option<Column> {
+        match name.trim().to_lowercase().as_str() {
+            "word" => Some(Column::Word),
+            "translated" => Some(Column::Translated),
+            "explanation" => Some(Column::Explanation),
+            "pronunciation" => Some(Column::Pronunciation),
+            "sentence" => Some(Column::Sentence),
+            "type" => Some(Column::Type),
+            "sentence_gap" => Some(Column::SentenceGap),
+            "gap_term" => Some(Column::GapTerm),
+            "tags" => Some(Column::Tags),
+            "-" | "" => Some(Column::Skip),
+            _ => None,
+        }
+    }
+
+    pub(super) fn set(&self, card: &mut CardData, value: &str) {
+        let optional = || Some(value.to_string()).filter(|v| !v.is_empty());
+        match self {
+            Column::Word => card.word = value.to_string(),
+            Column::Translated => card.translated = value.to_string(),
+            Column::Explanation => card.explanation = value.to_string(),
+            Column::Pronunciation => card.pronunciation = value.to_string(),
+            Column::Sentence => card.sentence = value.to_string(),
+            Column::Type => card.card_type = optional(),
+            Column::SentenceGap => card.sentence_gap = optional(),
+            Column::GapTerm => card.gap_term = optional(),
+            Column::Tags => card.tags = split_tags(value),
+            Column::Skip => {}
+        }
+    }
+}
+
+// Columns come from `columns` when given, otherwise from the header row.
+// With `columns` given the first row is still skipped if it repeats the column names.
+pub fn parse(
+    content: &str,
+    delimiter: u8,
+    columns: Option<&[String]>,
+    path: &str,
+) -> Result<Vec<CardData>, Vec<DeckError>> {
+    let mut reader = ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(content.as_bytes());
+    let mut records = reader.records().peekable();
+    let schema = |message: String, line: Option<usize>| {
+        let diagnostic = Diagnostic::new(path, message);
+        DeckError::Schema(match line {
+            Some(line) => diagnostic.at(content, line, None),
+            None => diagnostic,
+        })
+    };
+
+    let first_row = match records.peek() {
+        Some(Ok(record)) => Some(record.clone()),
+        _ => None,
+    };
+    let (mapping, skip_first_row) = match columns {
+        Some(columns) => {
+            let mapping = parse_columns(columns.iter().map(String::as_str))
+                .map_err(|e| vec![schema(format!("--columns: {}", e), None)])?;
+            let repeats_names = first_row.as_ref().is_some_and(|row| {
+                row.len() == columns.len()
+                    && row
+                        .iter()
+                        .zip(columns)
+                        .all(|(cell, name)| cell.trim().eq_ignore_ascii_case(name.trim()))
+            });
+            (mapping, repeats_names)
+        }
+        None => match &first_row {
+            Some(row) => (
+                parse_columns(row.iter()).map_err(|e| {
+                    vec![schema(
+                        format!(
+                            "{}; the first row must name the columns or use --columns",
+                            e
+                        ),
+                        Some(line(row)),
+                    )]
+                })?,
+                true,
+            ),
+            None => return Ok(vec![]),
+        },
+    };
+    if !mapping.contains(&Column::Word) || !mapping.contains(&Column::Translated) {
+        let line = first_row
+            .filter(|_| columns.is_none())
+            .map(|row| line(&row));
+        return Err(vec![schema(
+            "columns must include at least word and translated".to_string(),
+            line,
+        )]);
+    }
+    if skip_first_row {
+        records.next();
+    }
+
+    let mut cards = vec![];
+    let mut errors = vec![];
+    for record in records {
+        match record {
+            Ok(record) => match to_card(&record, &mapping) {
+                Ok(card) => cards.push(card),
+                Err(problem) => errors.push(schema(problem, Some(line(&record)))),
+            },
+            Err(e) => {
+                let diagnostic = Diagnostic::new(path, e.to_string());
+                errors.push(DeckError::Parse(match e.position() {
+                    Some(position) => diagnostic.at(content, position.line() as usize, None),
+                    None => diagnostic,
+                }))
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(cards)
+    } else {
+        Err(errors)
+    }
+}
+
+fn parse_columns<'a, I>(names: I) -> Result<Vec<Column>, String>
+where
+    I: Iterator<Item = &'a str>,
+{
+    names
+        .map(|name| Column::parse(name).ok_or_else(|| format!("unknown column '{}'", name)))
+        .collect()
+}
+
+fn to_card(record: &StringRecord, mapping: &[Column]) -> Result<CardData, String> {
+    if record.len() != mapping.len() {
+        return Err(format!(
+            "expected {} fields, found {}",
+            mapping.len(),
+            record.len()
+        ));
+    }
+
+    let mut card = CardData::default();
+    mapping
+        .iter()
+        .zip(record.iter())
+        .for_each(|(column, value)| column.set(&mut card, value.trim()));
+
+    if card.word.is_empty() {
+        Err("empty word".to_string())
+    } else if card.translated.is_empty() {
+        Err("empty translation".to_string())
+    } else {
+        Ok(card)
+    }
+}
+
+fn line(record: &StringRecord) -> usize {
+    record.position().map_or(1, |p| p.line() as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn columns(names: &str) -> Vec<String> {
+        names.split(',').map(String::from).collect()
+    }
+
+    fn errors(result: Result<Vec<CardData>, Vec<DeckError>>) -> Vec<String> {
+        result
+            .unwrap_err()
+            .iter()
+            .map(DeckError::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn reads_columns_from_header() {
+        let cards = parse(
+            "translated,word,type\ncat,kot,noun\ndog,pies,\n",
+            b',',
+            None,
+            "words.csv",
+        )
+        .unwrap();
+
+        assert_eq!(2, cards.len());
+        assert_eq!("kot", cards[0].word);
+        assert_eq!("cat", cards[0].translated);
+        assert_eq!(Some("noun"), cards[0].card_type.as_deref());
+        assert_eq!(None, cards[1].card_type);
+    }
+
+    #[test]
+    fn reads_columns_from_mapping() {
+        let cards = parse(
+            "kot\tcat\t-\n",
+            b'\t',
+            Some(&columns("word,translated,-")),
+            "words.tsv",
+        )
+        .unwrap();
+
+        assert_eq!("kot", cards[0].word);
+        assert_eq!("cat", cards[0].translated);
+    }
+
+    #[test]
+    fn tags_are_split() {
+        let cards = parse(
+            "word,translated,tags\nkot,cat,\"animals, pets week3\"\n",
+            b',',
+            None,
+            "words.csv",
+        )
+        .unwrap();
+
+        assert_eq!(vec!["animals", "pets", "week3"], cards[0].tags);
+    }
+
+    #[test]
+    fn header_repeating_mapping_is_skipped() {
+        let cards = parse(
+            "Word,Translated\nkot,cat\n",
+            b',',
+            Some(&columns("word,translated")),
+            "words.csv",
+        )
+        .unwrap();
+
+        assert_eq!(1, cards.len());
+    }
+
+    #[test]
+    fn quoted_fields_with_newlines() {
+        let cards = parse(
+            "word,translated,sentence\n\"kot, domowy\",cat,\"Line one\nline two\"\n",
+            b',',
+            None,
+            "words.csv",
+        )
+        .unwrap();
+
+        assert_eq!("kot, domowy", cards[0].word);
+        assert_eq!("Line one\nline two", cards[0].sentence);
+    }
+
+    #[test]
+    fn bad_rows_are_reported_with_line_numbers() {
+        let result = parse(
+            "word,translated\nkot,cat\n\"multi\nline\",\nok,fine\nshort\n",
+            b',',
+            None,
+            "words.csv",
+        );
+
+        assert_eq!(
+            vec![
+                "words.csv:3: empty translation\n3 | \"multi",
+                "words.csv:6: expected 2 fields, found 1\n6 | short"
+            ],
+            errors(result)
+        );
+    }
+
+    #[test]
+    fn unknown_header_is_reported() {
+        assert_eq!(
+            vec!["words.csv:1: unknown column 'kot'; the first row must name the columns or use --columns\n1 | kot,cat"],
+            errors(parse("kot,cat\n", b',', None, "words.csv"))
+        );
+    }
+
+    #[test]
+    fn word_and_translation_columns_are_required() {
+        let result = parse(
+            "a,b\n",
+            b',',
+            Some(&columns("word,explanation")),
+            "words.csv",
+        );
+
+        assert_eq!(
+            vec!["words.csv: columns must include at least word and translated"],
+            errors(result)
+        );
+    }
+}
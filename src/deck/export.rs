@@ -0,0 +1,191 @@
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+
+use csv::WriterBuilder;
+
+use crate::card::card_data::CardData;
+use crate::error::Error;
+
+const CSV_HEADER: [&str; 9] = [
+    "word",
+    "translated",
+    "explanation",
+    "pronunciation",
+    "sentence",
+    "type",
+    "sentence_gap",
+    "gap_term",
+    "tags",
+];
+// Lines Anki reads before the notes, see "Importing text files" in the Anki manual
+const ANKI_HEADER: &str = "#separator:tab\n#html:false\n#tags column:6\n";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Anki,
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    pub fn detect<P: AsRef<Path>>(path: P) -> ExportFormat {
+        let extension = path
+            .as_ref()
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase());
+        match extension.as_deref() {
+            Some("csv") => ExportFormat::Csv,
+            Some("json") => ExportFormat::Json,
+            _ => ExportFormat::Anki,
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "anki" => Ok(ExportFormat::Anki),
+            "csv" => Ok(ExportFormat::Csv),
+            "json" => Ok(ExportFormat::Json),
+            _ => Err(format!("Unknown export format: {}", s)),
+        }
+    }
+}
+
+pub fn write_cards<W: Write>(
+    cards: &[&CardData],
+    format: ExportFormat,
+    mut writer: W,
+) -> Result<(), Error> {
+    match format {
+        ExportFormat::Anki => {
+            writer.write_all(ANKI_HEADER.as_bytes())?;
+            let mut writer = WriterBuilder::new().delimiter(b'\t').from_writer(writer);
+            for card in cards {
+                writer.write_record([
+                    &card.word,
+                    &card.translated,
+                    &card.explanation,
+                    &card.pronunciation,
+                    &card.sentence,
+                    &tags(card),
+                ])?;
+            }
+            writer.flush()?;
+        }
+        ExportFormat::Csv => {
+            let mut writer = WriterBuilder::new().from_writer(writer);
+            writer.write_record(CSV_HEADER)?;
+            for card in cards {
+                let optional = |value: &Option<String>| value.clone().unwrap_or_default();
+                writer.write_record([
+                    card.word.clone(),
+                    card.translated.clone(),
+                    card.explanation.clone(),
+                    card.pronunciation.clone(),
+                    card.sentence.clone(),
+                    optional(&card.card_type),
+                    optional(&card.sentence_gap),
+                    optional(&card.gap_term),
+                    card.tags.join(" "),
+                ])?;
+            }
+            writer.flush()?;
+        }
+        ExportFormat::Json => {
+            serde_json::to_writer_pretty(&mut writer, cards)?;
+            writer.write_all(b"\n")?;
+        }
+    }
+    Ok(())
+}
+
+// Anki tags are separated by spaces; the type is a tag too
+fn tags(card: &CardData) -> String {
+    card.card_type
+        .iter()
+        .chain(card.tags.iter())
+        .map(|tag| tag.split_whitespace().collect::<Vec<_>>().join("_"))
+        .filter(|tag| !tag.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deck::delimited;
+
+    fn card(word: &str, translated: &str, card_type: Option<&str>) -> CardData {
+        CardData {
+            word: word.to_string(),
+            translated: translated.to_string(),
+            card_type: card_type.map(String::from),
+            ..CardData::default()
+        }
+    }
+
+    fn export(cards: &[CardData], format: ExportFormat) -> String {
+        let mut output = vec![];
+        write_cards(&cards.iter().collect::<Vec<_>>(), format, &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn format_detected_by_extension() {
+        assert_eq!(ExportFormat::Csv, ExportFormat::detect("hard.csv"));
+        assert_eq!(ExportFormat::Json, ExportFormat::detect("hard.JSON"));
+        assert_eq!(ExportFormat::Anki, ExportFormat::detect("hard.txt"));
+    }
+
+    #[test]
+    fn anki_text_with_tags() {
+        let cards = vec![
+            CardData {
+                tags: vec!["animals".to_string(), "week 3".to_string()],
+                ..card("kot", "cat", Some("noun"))
+            },
+            card("zaraz", "in a moment, soon", Some("phrasal verb")),
+        ];
+
+        assert_eq!(
+            "#separator:tab\n#html:false\n#tags column:6\n\
+             kot\tcat\t\t\t\tnoun animals week_3\n\
+             zaraz\tin a moment, soon\t\t\t\tphrasal_verb\n",
+            export(&cards, ExportFormat::Anki)
+        );
+    }
+
+    #[test]
+    fn csv_can_be_read_back() {
+        let cards = vec![
+            card("kot, domowy", "cat", Some("noun")),
+            CardData {
+                tags: vec!["animals".to_string(), "pets".to_string()],
+                ..card("pies", "dog", None)
+            },
+        ];
+
+        let exported = export(&cards, ExportFormat::Csv);
+
+        assert_eq!(
+            cards,
+            delimited::parse(&exported, b',', None, "words.csv").unwrap()
+        );
+    }
+
+    #[test]
+    fn json_can_be_read_back() {
+        let cards = vec![card("kot", "cat", Some("noun"))];
+
+        let exported = export(&cards, ExportFormat::Json);
+
+        assert_eq!(
+            cards,
+            serde_json::from_str::<Vec<CardData>>(&exported).unwrap()
+        );
+    }
+}
@@ -0,0 +1,154 @@
+use serde_json::error::Category;
+use serde_json::value::RawValue;
+
+use crate::card::card_data::CardData;
+use crate::error::{DeckError, Diagnostic};
+
+// Cards are checked one by one, so a single bad card does not hide the problems of the others
+pub fn parse(content: &str, path: &str) -> Result<Vec<CardData>, Vec<DeckError>> {
+    parse_cards(content, path, false)
+}
+
+// Cards without a word or translation can be studied and fixed in the app,
+// they are reported only when the deck is checked
+pub fn check(content: &str, path: &str) -> Result<Vec<CardData>, Vec<DeckError>> {
+    parse_cards(content, path, true)
+}
+
+fn parse_cards(
+    content: &str,
+    path: &str,
+    check_fields: bool,
+) -> Result<Vec<CardData>, Vec<DeckError>> {
+    let elements: Vec<&RawValue> = serde_json::from_str(content).map_err(|e| {
+        let diagnostic = diagnostic(path, content, 0, "", &e);
+        vec![match e.classify() {
+            Category::Data => DeckError::Schema(Diagnostic {
+                message: format!("expected a list of cards: {}", diagnostic.message),
+                ..diagnostic
+            }),
+            _ => DeckError::Parse(diagnostic),
+        }]
+    })?;
+
+    let mut cards = vec![];
+    let mut errors = vec![];
+    for (nr, element) in elements.iter().map(|element| element.get()).enumerate() {
+        let start = element.as_ptr() as usize - content.as_ptr() as usize;
+        let prefix = format!("card {}: ", nr + 1);
+        match serde_json::from_str::<CardData>(element) {
+            Ok(card) if check_fields && card.word.trim().is_empty() => {
+                errors.push(schema(path, content, start, prefix + "empty word"))
+            }
+            Ok(card) if check_fields && card.translated.trim().is_empty() => {
+                errors.push(schema(path, content, start, prefix + "empty translation"))
+            }
+            Ok(card) => cards.push(card),
+            Err(e) => errors.push(DeckError::Schema(diagnostic(
+                path, content, start, &prefix, &e,
+            ))),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(cards)
+    } else {
+        Err(errors)
+    }
+}
+
+fn schema(path: &str, content: &str, offset: usize, message: String) -> DeckError {
+    let (line, column) = position(content, offset);
+    DeckError::Schema(Diagnostic::new(path, message).at(content, line, Some(column)))
+}
+
+// serde positions are relative to the parsed text
+fn diagnostic(
+    path: &str,
+    content: &str,
+    base: usize,
+    prefix: &str,
+    error: &serde_json::Error,
+) -> Diagnostic {
+    let text = &content[base..];
+    let (line, column) = position(content, base + offset(text, error.line(), error.column()));
+    let message = error.to_string();
+    let suffix = format!(" at line {} column {}", error.line(), error.column());
+    let message = message.strip_suffix(&suffix).unwrap_or(&message);
+    Diagnostic::new(path, format!("{}{}", prefix, message)).at(content, line, Some(column))
+}
+
+// Byte offset of serde's 1-based line and byte column
+fn offset(text: &str, line: usize, column: usize) -> usize {
+    let line_start: usize = text
+        .split_inclusive('\n')
+        .take(line.saturating_sub(1))
+        .map(str::len)
+        .sum();
+    let offset = (line_start + column.saturating_sub(1)).min(text.len());
+    (0..=offset)
+        .rev()
+        .find(|offset| text.is_char_boundary(*offset))
+        .unwrap_or(0)
+}
+
+// 1-based line and character column of a byte offset
+fn position(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset];
+    let line_start = before.rfind('\n').map_or(0, |nl| nl + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors(content: &str) -> Vec<String> {
+        check(content, "words.json")
+            .unwrap_err()
+            .iter()
+            .map(DeckError::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn syntax_error_has_position_and_snippet() {
+        assert_eq!(
+            vec!["words.json:3:1: expected `,` or `]`\n3 | {\"word\": \"pies\"}\n  | ^"],
+            errors("[\n{\"word\": \"kot\"}\n{\"word\": \"pies\"}\n]")
+        );
+    }
+
+    #[test]
+    fn every_invalid_card_is_reported() {
+        let content = "[\n  {\"word\": \"kot\", \"translated\": \"cat\", \"explanation\": \"\", \"pronunciation\": \"\", \"sentence\": \"\"},\n  {\"word\": \"pies\", \"translated\": 2},\n  {\"word\": \" \", \"translated\": \"mouse\", \"explanation\": \"\", \"pronunciation\": \"\", \"sentence\": \"\"},\n  {\"word\": \"ptak\"}\n]";
+
+        assert_eq!(
+            vec![
+                "words.json:3:34: card 2: invalid type: integer `2`, expected a string\n3 |   {\"word\": \"pies\", \"translated\": 2},\n  |                                  ^",
+                "words.json:4:3: card 3: empty word\n4 |   {\"word\": \" \", \"translated\": \"mouse\", \"explanation\": \"\", \"p...\n  |   ^",
+                "words.json:5:18: card 4: missing field `translated`\n5 |   {\"word\": \"ptak\"}\n  |                  ^",
+            ],
+            errors(content)
+        );
+    }
+
+    #[test]
+    fn cards_with_empty_fields_are_loaded() {
+        let content = "[{\"word\": \" \", \"translated\": \"mouse\", \"explanation\": \"\", \"pronunciation\": \"\", \"sentence\": \"\"}]";
+
+        assert_eq!("mouse", parse(content, "words.json").unwrap()[0].translated);
+        assert!(check(content, "words.json").is_err());
+    }
+
+    #[test]
+    fn deck_must_be_a_list() {
+        let errors = parse("{\"word\": \"kot\"}", "words.json").unwrap_err();
+
+        assert!(matches!(&errors[..], [DeckError::Schema(diagnostic)]
+            if diagnostic.message.starts_with("expected a list of cards: invalid type: map")));
+    }
+}
@@ -0,0 +1,356 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::info;
+use serde::Deserialize;
+
+use crate::card::card_data::{CardData, Visibility};
+use crate::card::filter::Filter;
+use crate::deck::{expand_inputs, interleave, read_deck_file, LoadOptions, StudyOptions};
+use crate::error::{DeckError, Diagnostic, Error};
+
+const MANIFEST_SUFFIX: &str = ".worm.toml";
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+struct ManifestFile {
+    name: Option<String>,
+    mode: Option<String>,
+    shuffle: Option<bool>,
+    visibility: Option<Visibility>,
+    limit: Option<usize>,
+    deck: Vec<DeckEntry>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct DeckEntry {
+    path: String,
+    #[serde(default = "default_weight")]
+    weight: usize,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    exclude_tags: Vec<String>,
+    #[serde(default)]
+    types: Vec<String>,
+    limit: Option<usize>,
+}
+
+fn default_weight() -> usize {
+    1
+}
+
+// A collection of decks studied together, e.g. one week of a course built from
+// shared vocabulary files; deck paths are relative to the manifest
+#[derive(Debug, PartialEq)]
+pub struct Manifest {
+    pub name: String,
+    pub options: StudyOptions,
+    pub limit: Option<usize>,
+    pub decks: Vec<IncludedDeck>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct IncludedDeck {
+    pub paths: Vec<PathBuf>,
+    pub weight: usize,
+    pub filter: Filter,
+    pub limit: Option<usize>,
+}
+
+pub fn is_manifest<P: AsRef<Path>>(path: P) -> bool {
+    path.as_ref()
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().ends_with(MANIFEST_SUFFIX))
+}
+
+impl Manifest {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::check(path).map_err(|mut errors| errors.remove(0).into())
+    }
+
+    // All problems of the manifest, pointing at the lines they are found in
+    pub fn check<P: AsRef<Path>>(path: P) -> Result<Self, Vec<DeckError>> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path).map_err(|error| {
+            vec![DeckError::Io {
+                path: path.display().to_string(),
+                error,
+            }]
+        })?;
+        Self::parse(&content, path)
+    }
+
+    fn parse(content: &str, path: &Path) -> Result<Self, Vec<DeckError>> {
+        let name = path.display().to_string();
+        let file: ManifestFile =
+            toml::from_str(content).map_err(|e| vec![parse_error(&name, content, &e)])?;
+        if file.deck.is_empty() {
+            return Err(vec![DeckError::Schema(Diagnostic::new(
+                &name,
+                "no [[deck]] included".to_string(),
+            ))]);
+        }
+
+        let mut errors = vec![];
+        let mode = match file.mode.as_deref().map(str::parse) {
+            Some(Ok(mode)) => Some(mode),
+            Some(Err(e)) => {
+                errors.push(schema(&name, content, "mode", "", e));
+                None
+            }
+            None => None,
+        };
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        let mut decks = vec![];
+        for entry in file.deck {
+            let error = |message: String| schema(&name, content, "path", &entry.path, message);
+            if entry.weight == 0 {
+                errors.push(error(format!(
+                    "weight of '{}' must be at least 1",
+                    entry.path
+                )));
+                continue;
+            }
+            let paths = match expand_inputs(&[dir.join(&entry.path).to_string_lossy()]) {
+                Ok(paths) => paths,
+                Err(e) => {
+                    errors.push(error(e.to_string()));
+                    continue;
+                }
+            };
+            if let Some(nested) = paths.iter().find(|path| is_manifest(path)) {
+                errors.push(error(format!(
+                    "'{}' is a manifest, manifests cannot be included",
+                    nested.display()
+                )));
+                continue;
+            }
+            decks.push(IncludedDeck {
+                paths,
+                weight: entry.weight,
+                filter: Filter {
+                    tags: entry.tags,
+                    exclude_tags: entry.exclude_tags,
+                    types: entry.types,
+                    decks: vec![],
+                },
+                limit: entry.limit,
+            });
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Ok(Manifest {
+            name: file.name.unwrap_or(name),
+            options: StudyOptions {
+                shuffle: file.shuffle,
+                mode,
+                visibility: file.visibility,
+            },
+            limit: file.limit,
+            decks,
+        })
+    }
+
+    pub fn deck_files(&self) -> impl Iterator<Item = &PathBuf> {
+        self.decks.iter().flat_map(|deck| deck.paths.iter())
+    }
+
+    // Limits take the first matching cards of a deck, then decks are interleaved
+    // by weight: a deck of weight 2 gives two cards for each card of a deck of weight 1
+    pub fn read_cards(&self, options: &LoadOptions) -> Result<Vec<CardData>, Error> {
+        let mut decks = vec![];
+        for deck in &self.decks {
+            let mut cards = vec![];
+            for path in &deck.paths {
+                cards.append(&mut read_deck_file(path, options)?);
+            }
+            cards.retain(|card| deck.filter.matches(card));
+            if let Some(limit) = deck.limit {
+                cards.truncate(limit);
+            }
+            decks.push((cards, deck.weight));
+        }
+        let mut cards = interleave(decks);
+        if let Some(limit) = self.limit {
+            cards.truncate(limit);
+        }
+        info!(
+            "Read set of {} cards from manifest: {}",
+            cards.len(),
+            self.name
+        );
+        Ok(cards)
+    }
+}
+
+// toml positions are 0-based
+fn parse_error(path: &str, content: &str, error: &toml::de::Error) -> DeckError {
+    let message = error.to_string();
+    let diagnostic = match error.line_col() {
+        Some((line, column)) => {
+            let suffix = format!(" at line {} column {}", line + 1, column + 1);
+            let message = message.strip_suffix(&suffix).unwrap_or(&message);
+            Diagnostic::new(path, format!("invalid manifest: {}", message)).at(
+                content,
+                line + 1,
+                Some(column + 1),
+            )
+        }
+        None => Diagnostic::new(path, format!("invalid manifest: {}", message)),
+    };
+    DeckError::Parse(diagnostic)
+}
+
+// Points at the first `key = ...` line with `value` in it
+fn schema(path: &str, content: &str, key: &str, value: &str, message: String) -> DeckError {
+    let diagnostic = Diagnostic::new(path, message);
+    let line = content.lines().position(|line| {
+        let line = line.trim_start();
+        line.starts_with(key) && line.contains(value)
+    });
+    DeckError::Schema(match line {
+        Some(nr) => diagnostic.at(content, nr + 1, None),
+        None => diagnostic,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::card_data::Mode;
+
+    fn write_deck(dir: &Path, name: &str, cards: &[(&str, &str, &[&str])]) {
+        let cards: Vec<CardData> = cards
+            .iter()
+            .map(|(word, card_type, tags)| CardData {
+                word: word.to_string(),
+                translated: word.to_uppercase(),
+                card_type: Some(card_type.to_string()),
+                tags: tags.iter().map(|tag| tag.to_string()).collect(),
+                ..CardData::default()
+            })
+            .collect();
+        fs::write(dir.join(name), serde_json::to_string(&cards).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn decks_are_filtered_limited_and_weighted() {
+        let dir = tempfile::tempdir().unwrap();
+        write_deck(
+            dir.path(),
+            "nouns.json",
+            &[
+                ("kot", "noun", &["animals"]),
+                ("dom", "noun", &[]),
+                ("pies", "noun", &["animals"]),
+                ("koń", "noun", &["animals", "archaic"]),
+                ("mysz", "noun", &["animals"]),
+            ],
+        );
+        write_deck(
+            dir.path(),
+            "verbs.json",
+            &[
+                ("iść", "verb", &[]),
+                ("mieć", "verb", &[]),
+                ("być", "verb", &[]),
+            ],
+        );
+        let path = dir.path().join("week3.worm.toml");
+        fs::write(
+            &path,
+            "name = \"B2 week 3\"\nmode = \"type\"\n\n[visibility]\nshow_example = true\n\n\
+             [[deck]]\npath = \"nouns.json\"\nweight = 2\ntags = [\"animals\"]\nexclude_tags = [\"archaic\"]\n\n\
+             [[deck]]\npath = \"verbs.json\"\nlimit = 2\n",
+        )
+        .unwrap();
+
+        let manifest = Manifest::load(&path).unwrap();
+        let cards = manifest.read_cards(&LoadOptions::default()).unwrap();
+
+        assert_eq!("B2 week 3", manifest.name);
+        assert_eq!(Some(Mode::Type), manifest.options.mode);
+        assert!(manifest.options.visibility.unwrap().show_example);
+        let words: Vec<&str> = cards.iter().map(|card| card.word.as_str()).collect();
+        assert_eq!(vec!["kot", "pies", "iść", "mysz", "mieć"], words);
+        assert_eq!(Some(dir.path().join("verbs.json")), cards[2].source);
+    }
+
+    #[test]
+    fn invalid_manifests_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        write_deck(dir.path(), "nouns.json", &[("kot", "noun", &[])]);
+        fs::write(dir.path().join("other.worm.toml"), "").unwrap();
+        let errors = |content: &str| -> Vec<(Option<usize>, String)> {
+            Manifest::parse(content, &dir.path().join("plan.worm.toml"))
+                .unwrap_err()
+                .into_iter()
+                .map(|error| match error {
+                    DeckError::Parse(diagnostic) | DeckError::Schema(diagnostic) => {
+                        (diagnostic.line, diagnostic.message)
+                    }
+                    error => (None, error.to_string()),
+                })
+                .collect()
+        };
+
+        assert_eq!(
+            vec![(None, "no [[deck]] included".to_string())],
+            errors("name = \"plan\"")
+        );
+        assert_eq!(
+            vec![(
+                Some(2),
+                "weight of 'nouns.json' must be at least 1".to_string()
+            )],
+            errors("[[deck]]\npath = \"nouns.json\"\nweight = 0")
+        );
+        let nested = errors("[[deck]]\npath = \"other.worm.toml\"");
+        assert_eq!(Some(2), nested[0].0);
+        assert!(nested[0].1.ends_with("manifests cannot be included"));
+        let unknown = errors("[[deck]]\npath = \"nouns.json\"\nsize = 2");
+        assert!(unknown[0].1.contains("unknown field `size`"));
+    }
+
+    #[test]
+    fn all_problems_of_a_manifest_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        write_deck(dir.path(), "nouns.json", &[("kot", "noun", &[])]);
+        let path = dir.path().join("plan.worm.toml");
+        fs::write(
+            &path,
+            "mode = \"quiz\"\n\n[[deck]]\npath = \"nouns.json\"\nweight = 0\n\n[[deck]]\npath = \"*.csv\"\n",
+        )
+        .unwrap();
+
+        let lines: Vec<Option<usize>> = Manifest::check(&path)
+            .unwrap_err()
+            .into_iter()
+            .map(|error| match error {
+                DeckError::Schema(diagnostic) => diagnostic.line,
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(vec![Some(1), Some(4), Some(8)], lines);
+    }
+
+    #[test]
+    fn manifest_syntax_errors_have_positions() {
+        let error = Manifest::parse("[[deck]\npath = 1", Path::new("plan.worm.toml"))
+            .unwrap_err()
+            .remove(0);
+
+        match error {
+            DeckError::Parse(diagnostic) => {
+                assert_eq!(Some(1), diagnostic.line);
+                assert!(diagnostic.message.starts_with("invalid manifest: "));
+            }
+            error => panic!("unexpected error {}", error),
+        }
+    }
+}
@@ -0,0 +1,565 @@
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use log::info;
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+
+use crate::card::card_data::{CardData, CardSet, Mode, Visibility};
+use crate::card::filter::Filter;
+use crate::deck::manifest::{is_manifest, Manifest};
+use crate::error::{DeckError, Error};
+use crate::scheduler::progress;
+use crate::scheduler::Scheduler;
+use crate::session::SessionState;
+
+pub mod anki;
+pub mod delimited;
+pub mod export;
+pub mod json;
+pub mod manifest;
+pub mod rewrite;
+
+const STDIN: &str = "stdin";
+const DECK_EXTENSIONS: &[&str] = &["json", "csv", "tsv", "tab"];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Csv,
+    Tsv,
+}
+
+impl Format {
+    pub fn detect<P: AsRef<Path>>(path: P) -> Format {
+        let extension = path
+            .as_ref()
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase());
+        match extension.as_deref() {
+            Some("csv") => Format::Csv,
+            Some("tsv") | Some("tab") => Format::Tsv,
+            _ => Format::Json,
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            "tsv" => Ok(Format::Tsv),
+            _ => Err(format!("Unknown format: {}", s)),
+        }
+    }
+}
+
+// How the cards of several decks are put together
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Merge {
+    #[default]
+    Concat,
+    Interleave,
+}
+
+impl FromStr for Merge {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "concat" => Ok(Merge::Concat),
+            "interleave" => Ok(Merge::Interleave),
+            _ => Err(format!("Unknown merge: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct LoadOptions {
+    pub format: Option<Format>,
+    pub columns: Option<Vec<String>>,
+    pub merge: Merge,
+    // Applied to the card set, it can be changed while studying
+    pub filter: Filter,
+}
+
+// Unset options are taken from a manifest, then from the defaults of the caller
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct StudyOptions {
+    pub shuffle: Option<bool>,
+    pub mode: Option<Mode>,
+    pub visibility: Option<Visibility>,
+}
+
+impl StudyOptions {
+    pub fn or(self, other: StudyOptions) -> StudyOptions {
+        StudyOptions {
+            shuffle: self.shuffle.or(other.shuffle),
+            mode: self.mode.or(other.mode),
+            visibility: self.visibility.or(other.visibility),
+        }
+    }
+}
+
+// Decks as given to a session, with each manifest among them read once
+#[derive(Debug, Default)]
+pub struct Decks {
+    decks: Vec<(PathBuf, Option<Manifest>)>,
+}
+
+impl Decks {
+    pub fn load(inputs: &[PathBuf]) -> Result<Self, Error> {
+        let decks = inputs
+            .iter()
+            .map(|input| {
+                let manifest = if is_manifest(input) {
+                    Some(Manifest::load(input)?)
+                } else {
+                    None
+                };
+                Ok((input.clone(), manifest))
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(Self { decks })
+    }
+
+    // Manifests with problems are left out, the problems are returned with the decks
+    pub fn check(inputs: &[PathBuf]) -> (Self, Vec<DeckError>) {
+        let mut errors = vec![];
+        let decks = inputs
+            .iter()
+            .filter_map(|input| {
+                if !is_manifest(input) {
+                    return Some((input.clone(), None));
+                }
+                match Manifest::check(input) {
+                    Ok(manifest) => Some((input.clone(), Some(manifest))),
+                    Err(mut found) => {
+                        errors.append(&mut found);
+                        None
+                    }
+                }
+            })
+            .collect();
+        (Self { decks }, errors)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.decks.is_empty()
+    }
+
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.decks.iter().map(|(path, _)| path.clone()).collect()
+    }
+
+    // Manifests are replaced by the deck files they include
+    pub fn files(&self) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = vec![];
+        for (path, manifest) in &self.decks {
+            let found = match manifest {
+                Some(manifest) => manifest.deck_files().cloned().collect(),
+                None => vec![path.clone()],
+            };
+            found.into_iter().for_each(|file| {
+                if !files.contains(&file) {
+                    files.push(file);
+                }
+            });
+        }
+        files
+    }
+
+    // The first manifest sets the options of the session
+    pub fn options(&self) -> StudyOptions {
+        self.decks
+            .iter()
+            .find_map(|(_, manifest)| manifest.as_ref())
+            .map(|manifest| manifest.options)
+            .unwrap_or_default()
+    }
+}
+
+// Directories give the deck files in them and patterns like "decks/*.json" the files
+// they match, both in name order; progress files next to the decks are left out
+pub fn expand_inputs<S: AsRef<str>>(inputs: &[S]) -> Result<Vec<PathBuf>, Error> {
+    let mut paths: Vec<PathBuf> = vec![];
+    for input in inputs.iter().map(AsRef::as_ref) {
+        let path = Path::new(input);
+        let found = if path.is_dir() {
+            let mut found: Vec<PathBuf> = fs::read_dir(path)?
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.is_file() && is_deck_file(path))
+                .collect();
+            found.sort();
+            found
+        } else if !path.exists() && input.contains(['*', '?', '[']) {
+            let options = glob::MatchOptions {
+                require_literal_leading_dot: true,
+                ..glob::MatchOptions::new()
+            };
+            glob::glob_with(input, options)
+                .map_err(|e| format!("Invalid pattern '{}': {}", input, e))?
+                .filter_map(Result::ok)
+                .filter(|path| path.is_file() && !progress::is_progress_file(path))
+                .collect()
+        } else {
+            vec![path.to_path_buf()]
+        };
+        if found.is_empty() {
+            return Err(format!("No deck files found for '{}'", input).into());
+        }
+        found.into_iter().for_each(|path| {
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        });
+    }
+    Ok(paths)
+}
+
+fn is_deck_file(path: &Path) -> bool {
+    let hidden = path
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    !hidden && DECK_EXTENSIONS.contains(&extension.as_str()) && !progress::is_progress_file(path)
+}
+
+pub fn read_cards_from_file<P: AsRef<Path>>(
+    path: P,
+    options: &LoadOptions,
+) -> Result<Vec<CardData>, Error> {
+    read_file(path.as_ref(), options, false).map_err(first_error)
+}
+
+pub fn read_cards_from_stdin(options: &LoadOptions) -> Result<Vec<CardData>, Error> {
+    let mut buffer = String::new();
+    io::stdin()
+        .read_to_string(&mut buffer)
+        .map_err(|error| DeckError::Io {
+            path: STDIN.to_string(),
+            error,
+        })?;
+    parse_cards(
+        &buffer,
+        options.format.unwrap_or(Format::Json),
+        options,
+        STDIN,
+        false,
+    )
+    .map_err(first_error)
+}
+
+// All problems of the deck instead of the first one; an empty deck can be
+// studied, cards may be added to it, but it is reported here
+pub fn check_file<P: AsRef<Path>>(
+    path: P,
+    options: &LoadOptions,
+) -> Result<Vec<CardData>, Vec<DeckError>> {
+    let path = path.as_ref();
+    let cards = read_file(path, options, true)?;
+    if cards.is_empty() {
+        return Err(vec![DeckError::Empty {
+            path: path.display().to_string(),
+        }]);
+    }
+    Ok(cards)
+}
+
+fn read_file(
+    path: &Path,
+    options: &LoadOptions,
+    check: bool,
+) -> Result<Vec<CardData>, Vec<DeckError>> {
+    let name = path.display().to_string();
+    let format = options.format.unwrap_or_else(|| Format::detect(path));
+    let content = fs::read_to_string(path).map_err(|error| {
+        vec![DeckError::Io {
+            path: name.clone(),
+            error,
+        }]
+    })?;
+    parse_cards(&content, format, options, &name, check)
+}
+
+fn first_error(mut errors: Vec<DeckError>) -> Error {
+    errors.remove(0).into()
+}
+
+fn parse_cards(
+    content: &str,
+    format: Format,
+    options: &LoadOptions,
+    path: &str,
+    check: bool,
+) -> Result<Vec<CardData>, Vec<DeckError>> {
+    let content = content.trim_start_matches('\u{feff}');
+    match format {
+        Format::Json if check => json::check(content, path),
+        Format::Json => json::parse(content, path),
+        Format::Csv => delimited::parse(content, b',', options.columns.as_deref(), path),
+        Format::Tsv => delimited::parse(content, b'\t', options.columns.as_deref(), path),
+    }
+}
+
+// Without decks the cards are read from stdin; a manifest is read like any deck
+pub fn read_card_set(
+    decks: &Decks,
+    load_options: &LoadOptions,
+    options: StudyOptions,
+    defaults: StudyOptions,
+    scheduler: Box<dyn Scheduler>,
+    session: &SessionState,
+) -> Result<CardSet, Error> {
+    let options = options.or(decks.options()).or(defaults);
+    let shuffle_cards = options.shuffle.unwrap_or(false);
+    let (name, cards) = if decks.is_empty() {
+        info!("Reading from stdin");
+        let cards = read_cards_from_stdin(load_options)?;
+        info!("Read set of {} cards from stdin", cards.len());
+        (STDIN.to_string(), cards)
+    } else {
+        let mut names = vec![];
+        let mut read = vec![];
+        for (path, manifest) in &decks.decks {
+            let (name, cards) = read_deck(path, manifest.as_ref(), load_options)?;
+            names.push(name);
+            read.push(cards);
+        }
+        (names.join(", "), merge(read, load_options.merge))
+    };
+    let cards = if shuffle_cards && session.is_resumable() {
+        info!("restoring card order of the last session");
+        session.restore_order(cards)
+    } else {
+        shuffle(cards, shuffle_cards)
+    };
+
+    let card_set = CardSet::new(&name, cards)
+        .shuffle_rounds(shuffle_cards)
+        .scheduler(scheduler)
+        .mode(options.mode.unwrap_or(Mode::Flip))
+        .visibility(options.visibility.unwrap_or_default())
+        .filter(load_options.filter.clone());
+    Ok(session.restore(card_set))
+}
+
+fn read_deck(
+    path: &Path,
+    manifest: Option<&Manifest>,
+    options: &LoadOptions,
+) -> Result<(String, Vec<CardData>), Error> {
+    if let Some(manifest) = manifest {
+        let cards = manifest.read_cards(options)?;
+        return Ok((manifest.name.clone(), cards));
+    }
+    let cards = read_deck_file(path, options)?;
+    info!(
+        "Read set of {} cards from file: {}",
+        cards.len(),
+        path.display()
+    );
+    Ok((path.display().to_string(), cards))
+}
+
+// Cards remember the file they were read from and their place in it
+fn read_deck_file(path: &Path, options: &LoadOptions) -> Result<Vec<CardData>, Error> {
+    let mut cards = read_cards_from_file(path, options)?;
+    cards.iter_mut().enumerate().for_each(|(position, card)| {
+        card.source = Some(path.to_path_buf());
+        card.position = Some(position);
+    });
+    Ok(cards)
+}
+
+fn merge(decks: Vec<Vec<CardData>>, merge: Merge) -> Vec<CardData> {
+    match merge {
+        Merge::Concat => decks.into_iter().flatten().collect(),
+        Merge::Interleave => interleave(decks.into_iter().map(|cards| (cards, 1)).collect()),
+    }
+}
+
+// Each turn takes as many cards of a deck as its weight
+fn interleave(decks: Vec<(Vec<CardData>, usize)>) -> Vec<CardData> {
+    let mut decks: Vec<_> = decks
+        .into_iter()
+        .map(|(cards, weight)| (cards.into_iter(), weight))
+        .collect();
+    let mut cards = vec![];
+    loop {
+        let round: Vec<CardData> = decks
+            .iter_mut()
+            .flat_map(|(deck, weight)| deck.take(*weight))
+            .collect();
+        if round.is_empty() {
+            return cards;
+        }
+        cards.extend(round);
+    }
+}
+
+fn shuffle(mut data: Vec<CardData>, shuffle_cards: bool) -> Vec<CardData> {
+    if shuffle_cards {
+        info!("shuffling card set");
+        data.shuffle(&mut thread_rng());
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scheduler::Linear;
+
+    #[test]
+    fn format_detected_by_extension() {
+        assert_eq!(Format::Csv, Format::detect("words.CSV"));
+        assert_eq!(Format::Tsv, Format::detect("dir/words.tsv"));
+        assert_eq!(Format::Json, Format::detect("words.json"));
+        assert_eq!(Format::Json, Format::detect("words"));
+    }
+
+    #[test]
+    fn json_with_bom() {
+        let cards = parse_cards(
+            "\u{feff}[{\"word\": \"kot\", \"translated\": \"cat\", \"explanation\": \"\", \"pronunciation\": \"\", \"sentence\": \"\"}]",
+            Format::Json,
+            &LoadOptions::default(),
+            "words.json",
+            false,
+        )
+        .unwrap();
+
+        assert_eq!("kot", cards[0].word);
+    }
+
+    fn words(cards: &[CardData]) -> Vec<&str> {
+        cards.iter().map(|card| card.word.as_str()).collect()
+    }
+
+    fn deck(words: &[&str]) -> Vec<CardData> {
+        words
+            .iter()
+            .map(|word| CardData {
+                word: word.to_string(),
+                translated: word.to_uppercase(),
+                ..CardData::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn decks_are_concatenated_or_interleaved() {
+        let decks = || {
+            vec![
+                deck(&["a1", "a2", "a3"]),
+                deck(&["b1"]),
+                deck(&["c1", "c2"]),
+            ]
+        };
+
+        assert_eq!(
+            vec!["a1", "a2", "a3", "b1", "c1", "c2"],
+            words(&merge(decks(), Merge::Concat))
+        );
+        assert_eq!(
+            vec!["a1", "b1", "c1", "a2", "c2", "a3"],
+            words(&merge(decks(), Merge::Interleave))
+        );
+    }
+
+    #[test]
+    fn directories_and_patterns_are_expanded() {
+        let dir = tempfile::tempdir().unwrap();
+        [
+            "b.json",
+            "a.csv",
+            "a.csv.progress.json",
+            "notes.txt",
+            ".hidden.json",
+        ]
+        .iter()
+        .for_each(|name| fs::write(dir.path().join(name), "").unwrap());
+        let dir_name = dir.path().display().to_string();
+        let pattern = format!("{}/*.json", dir_name);
+        let single = format!("{}/b.json", dir_name);
+
+        let names = |inputs: &[&str]| -> Vec<String> {
+            expand_inputs(inputs)
+                .unwrap()
+                .iter()
+                .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+                .collect()
+        };
+
+        assert_eq!(vec!["a.csv", "b.json"], names(&[&dir_name]));
+        assert_eq!(vec!["b.json"], names(&[&pattern]));
+        assert_eq!(vec!["b.json", "a.csv"], names(&[&single, &dir_name]));
+        assert!(expand_inputs(&[format!("{}/*.tsv", dir_name)]).is_err());
+    }
+
+    #[test]
+    fn manifest_sets_options_of_card_set() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("words.json"),
+            serde_json::to_string(&deck(&["kot", "pies"])).unwrap(),
+        )
+        .unwrap();
+        let manifest = dir.path().join("plan.worm.toml");
+        fs::write(
+            &manifest,
+            "mode = \"type\"\n\n[visibility]\nshow_example = true\n\n[[deck]]\npath = \"words.json\"\n",
+        )
+        .unwrap();
+        let decks = Decks::load(&[manifest]).unwrap();
+        let read = |options: StudyOptions| {
+            let defaults = StudyOptions {
+                shuffle: Some(false),
+                mode: Some(Mode::Choice),
+                visibility: Some(Visibility::default()),
+            };
+            read_card_set(
+                &decks,
+                &LoadOptions::default(),
+                options,
+                defaults,
+                Box::new(Linear),
+                &SessionState::default(),
+            )
+            .unwrap()
+        };
+
+        let card_set = read(StudyOptions::default());
+        assert_eq!(Mode::Type, card_set.get_mode());
+        assert!(card_set.get_visibility().show_example);
+        assert_eq!(vec![dir.path().join("words.json")], decks.files());
+
+        let card_set = read(StudyOptions {
+            mode: Some(Mode::Cloze),
+            ..StudyOptions::default()
+        });
+        assert_eq!(Mode::Cloze, card_set.get_mode());
+    }
+
+    #[test]
+    fn empty_deck_is_reported_by_check() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("words.json");
+        fs::write(&path, "[]").unwrap();
+
+        assert!(read_cards_from_file(&path, &LoadOptions::default())
+            .unwrap()
+            .is_empty());
+        let errors = check_file(&path, &LoadOptions::default()).unwrap_err();
+        assert!(matches!(&errors[..], [DeckError::Empty { .. }]));
+    }
+}
@@ -0,0 +1,348 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use serde::Serialize;
+use serde_json::ser::PrettyFormatter;
+use serde_json::value::RawValue;
+use serde_json::{Map, Value};
+use tempfile::NamedTempFile;
+
+use crate::card::card_data::CardData;
+use crate::deck::export::{write_cards, ExportFormat};
+use crate::error::Error;
+
+const CARD_FIELDS: &[&str] = &[
+    "word",
+    "translated",
+    "explanation",
+    "pronunciation",
+    "sentence",
+    "type",
+    "sentence_gap",
+    "gap_term",
+    "tags",
+];
+const DEFAULT_INDENT: &str = "  ";
+
+// Only the JSON object of the edited card is rewritten, the rest of the file
+// keeps its formatting and values the edit leaves alone keep their text
+pub fn update_card<P: AsRef<Path>>(path: P, old: &CardData, new: &CardData) -> Result<(), Error> {
+    let path = path.as_ref();
+    let content = fs::read_to_string(path)?;
+    let updated = replace_card(&content, old, new)
+        .map_err(|e| format!("Cannot update {}: {}", path.display(), e))?;
+    write_atomically(path, updated.as_bytes())
+}
+
+// The new card is formatted like the last one in the file, returns its position
+pub fn append_card<P: AsRef<Path>>(path: P, card: &CardData) -> Result<usize, Error> {
+    let path = path.as_ref();
+    let content = fs::read_to_string(path)?;
+    let (updated, position) = insert_last(&content, card)
+        .map_err(|e| format!("Cannot update {}: {}", path.display(), e))?;
+    write_atomically(path, updated.as_bytes())?;
+    Ok(position)
+}
+
+pub fn write_deck<P: AsRef<Path>>(path: P, cards: &[CardData]) -> Result<(), Error> {
+    let cards: Vec<&CardData> = cards.iter().collect();
+    let mut content = vec![];
+    write_cards(&cards, ExportFormat::Json, &mut content)?;
+    write_atomically(path.as_ref(), &content)
+}
+
+// The card is found by its position, duplicates of it may come earlier
+fn replace_card(content: &str, old: &CardData, new: &CardData) -> Result<String, Error> {
+    let json = content.trim_start_matches('\u{feff}');
+    let elements: Vec<&RawValue> = serde_json::from_str(json)?;
+    let id = old.id();
+    let element = old
+        .position
+        .and_then(|position| elements.get(position))
+        .map(|element| element.get())
+        .filter(|element| {
+            serde_json::from_str::<CardData>(element)
+                .map(|card| card.id() == id)
+                .unwrap_or(false)
+        })
+        .ok_or_else(|| format!("card '{}' not found", old.word))?;
+
+    let start = offset(content, element);
+    let end = start + element.len();
+    let mut object: Map<String, Value> = serde_json::from_str(element)?;
+    merge(&mut object, new)?;
+
+    Ok(format!(
+        "{}{}{}",
+        &content[..start],
+        format_like(element, &object, line_indent(content, start))?,
+        &content[end..]
+    ))
+}
+
+fn insert_last(content: &str, card: &CardData) -> Result<(String, usize), Error> {
+    let json = content.trim_start_matches('\u{feff}');
+    let elements: Vec<&RawValue> = serde_json::from_str(json)?;
+    let mut object = Map::new();
+    merge(&mut object, card)?;
+
+    let last = match elements.last() {
+        Some(last) => last.get(),
+        None => {
+            let content = format!("{}\n", serde_json::to_string_pretty(&[object])?);
+            return Ok((content, 0));
+        }
+    };
+    let start = offset(content, last);
+    let end = start + last.len();
+    let separator = match elements.len() {
+        1 if content[..start].ends_with(line_indent(content, start))
+            && content[..start].contains('\n') =>
+        {
+            format!(",\n{}", line_indent(content, start))
+        }
+        1 => ", ".to_string(),
+        n => {
+            let previous = elements[n - 2].get();
+            content[offset(content, previous) + previous.len()..start].to_string()
+        }
+    };
+
+    let updated = format!(
+        "{}{}{}{}",
+        &content[..end],
+        separator,
+        format_like(last, &object, line_indent(content, start))?,
+        &content[end..]
+    );
+    Ok((updated, elements.len()))
+}
+
+fn offset(content: &str, element: &str) -> usize {
+    element.as_ptr() as usize - content.as_ptr() as usize
+}
+
+fn merge(object: &mut Map<String, Value>, card: &CardData) -> Result<(), Error> {
+    let fields = match serde_json::to_value(card)? {
+        Value::Object(fields) => fields,
+        _ => return Err("card is not a JSON object".into()),
+    };
+    CARD_FIELDS
+        .iter()
+        .filter(|field| !fields.contains_key(**field))
+        .for_each(|field| {
+            object.shift_remove(*field);
+        });
+    fields.into_iter().for_each(|(field, value)| {
+        object.insert(field, value);
+    });
+    Ok(())
+}
+
+fn line_indent(content: &str, position: usize) -> &str {
+    let line_start = content[..position].rfind('\n').map_or(0, |nl| nl + 1);
+    let line = &content[line_start..position];
+    let indent_len = line.len() - line.trim_start().len();
+    &line[..indent_len]
+}
+
+// Values equal to the ones in `original` keep their text from there
+fn format_like(original: &str, object: &Map<String, Value>, indent: &str) -> Result<String, Error> {
+    let raw: HashMap<String, &RawValue> = serde_json::from_str(original)?;
+    let kept = |field: &str, value: &Value| {
+        raw.get(field)
+            .map(|raw| raw.get())
+            .filter(|raw| serde_json::from_str::<Value>(raw).ok().as_ref() == Some(value))
+    };
+    let colon = if original.contains("\": ") { ": " } else { ":" };
+    if !original.contains('\n') {
+        let comma = if original.contains(", \"") { ", " } else { "," };
+        let padding = if original.starts_with("{ ") { " " } else { "" };
+        let fields: Vec<String> = object
+            .iter()
+            .map(|(field, value)| {
+                let value = kept(field, value).map_or_else(|| value.to_string(), str::to_string);
+                format!("{}{}{}", Value::from(field.as_str()), colon, value)
+            })
+            .collect();
+        return Ok(format!("{{{}{}{}}}", padding, fields.join(comma), padding));
+    }
+
+    let field_indent = original
+        .lines()
+        .nth(1)
+        .map(|line| &line[..line.len() - line.trim_start().len()])
+        .and_then(|field_indent| field_indent.strip_prefix(indent))
+        .filter(|step| !step.is_empty())
+        .unwrap_or(DEFAULT_INDENT);
+    let newline = if original.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let line_start = format!("{}{}{}", newline, indent, field_indent);
+    let fields = object
+        .iter()
+        .map(|(field, value)| {
+            let value = match kept(field, value) {
+                Some(raw) => raw.to_string(),
+                None => pretty(value, field_indent)?
+                    .lines()
+                    .collect::<Vec<_>>()
+                    .join(&line_start),
+            };
+            Ok(format!("{}{}{}", Value::from(field.as_str()), colon, value))
+        })
+        .collect::<Result<Vec<String>, Error>>()?;
+    Ok(format!(
+        "{{{}{}{}{}}}",
+        line_start,
+        fields.join(&format!(",{}", line_start)),
+        newline,
+        indent
+    ))
+}
+
+fn pretty(value: &Value, indent: &str) -> Result<String, Error> {
+    let mut formatted = vec![];
+    let mut serializer = serde_json::Serializer::with_formatter(
+        &mut formatted,
+        PrettyFormatter::with_indent(indent.as_bytes()),
+    );
+    value.serialize(&mut serializer)?;
+    Ok(String::from_utf8_lossy(&formatted).into_owned())
+}
+
+// The file is replaced only once the new content is fully written
+fn write_atomically(path: &Path, content: &[u8]) -> Result<(), Error> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let mut file = NamedTempFile::new_in(dir)?;
+    file.write_all(content)?;
+    if let Ok(metadata) = fs::metadata(path) {
+        file.as_file().set_permissions(metadata.permissions())?;
+    }
+    file.persist(path).map_err(|e| e.error)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card(word: &str, translated: &str) -> CardData {
+        CardData {
+            word: word.to_string(),
+            translated: translated.to_string(),
+            ..CardData::default()
+        }
+    }
+
+    fn at(position: usize, card: CardData) -> CardData {
+        CardData {
+            position: Some(position),
+            ..card
+        }
+    }
+
+    fn edited(word: &str) -> CardData {
+        CardData {
+            card_type: Some("noun".to_string()),
+            ..card(word, "cat")
+        }
+    }
+
+    #[test]
+    fn only_edited_card_is_rewritten() {
+        let content = "[\n    {\n        \"word\": \"kot\",\n        \"note\": [1, 2],\n        \"translated\": \"cat\",\n        \"explanation\": \"\",\n        \"pronunciation\": \"\",\n        \"sentence\": \"\",\n        \"gap_term\": \"ko\"\n    },\n    {\"word\":\"pies\",\"translated\":\"dog\",\"explanation\":\"\",\"pronunciation\":\"\",\"sentence\":\"\"}\n]\n";
+
+        let updated = replace_card(content, &at(0, card("kot", "cat")), &edited("kotek")).unwrap();
+
+        assert_eq!(
+            "[\n    {\n        \"word\": \"kotek\",\n        \"note\": [1, 2],\n        \"translated\": \"cat\",\n        \"explanation\": \"\",\n        \"pronunciation\": \"\",\n        \"sentence\": \"\",\n        \"type\": \"noun\"\n    },\n    {\"word\":\"pies\",\"translated\":\"dog\",\"explanation\":\"\",\"pronunciation\":\"\",\"sentence\":\"\"}\n]\n",
+            updated
+        );
+    }
+
+    #[test]
+    fn inline_card_stays_inline() {
+        let content = "[{ \"word\": \"pies\", \"translated\": \"dog\", \"explanation\": \"\", \"pronunciation\": \"\", \"sentence\": \"\" },\n{\"extra\":true,\"word\":\"kot\",\"translated\":\"cat\",\"explanation\":\"\",\"pronunciation\":\"\",\"sentence\":\"\"}]";
+
+        let updated = replace_card(content, &at(1, card("kot", "cat")), &edited("kotek")).unwrap();
+
+        assert_eq!(
+            "[{ \"word\": \"pies\", \"translated\": \"dog\", \"explanation\": \"\", \"pronunciation\": \"\", \"sentence\": \"\" },\n{\"extra\":true,\"word\":\"kotek\",\"translated\":\"cat\",\"explanation\":\"\",\"pronunciation\":\"\",\"sentence\":\"\",\"type\":\"noun\"}]",
+            updated
+        );
+    }
+
+    #[test]
+    fn card_is_appended_like_the_last_one() {
+        let content = "[\n  {\"word\": \"kot\", \"translated\": \"cat\", \"explanation\": \"\", \"pronunciation\": \"\", \"sentence\": \"\"},\n  {\n    \"word\": \"pies\",\n    \"translated\": \"dog\",\n    \"explanation\": \"\",\n    \"pronunciation\": \"\",\n    \"sentence\": \"\"\n  }\n]\n";
+
+        let (updated, position) = insert_last(content, &edited("kotek")).unwrap();
+
+        assert_eq!(2, position);
+
+        assert_eq!(
+            "[\n  {\"word\": \"kot\", \"translated\": \"cat\", \"explanation\": \"\", \"pronunciation\": \"\", \"sentence\": \"\"},\n  {\n    \"word\": \"pies\",\n    \"translated\": \"dog\",\n    \"explanation\": \"\",\n    \"pronunciation\": \"\",\n    \"sentence\": \"\"\n  },\n  {\n    \"word\": \"kotek\",\n    \"translated\": \"cat\",\n    \"explanation\": \"\",\n    \"pronunciation\": \"\",\n    \"sentence\": \"\",\n    \"type\": \"noun\"\n  }\n]\n",
+            updated
+        );
+    }
+
+    #[test]
+    fn card_is_appended_to_single_and_empty_deck() {
+        let inline = "[{\"word\":\"kot\",\"translated\":\"cat\",\"explanation\":\"\",\"pronunciation\":\"\",\"sentence\":\"\"}]";
+        let (updated, _) = insert_last(inline, &card("pies", "dog")).unwrap();
+        assert_eq!(
+            "[{\"word\":\"kot\",\"translated\":\"cat\",\"explanation\":\"\",\"pronunciation\":\"\",\"sentence\":\"\"}, {\"word\":\"pies\",\"translated\":\"dog\",\"explanation\":\"\",\"pronunciation\":\"\",\"sentence\":\"\"}]",
+            updated
+        );
+
+        let (updated, position) = insert_last("[]", &card("pies", "dog")).unwrap();
+        assert_eq!(0, position);
+        let cards: Vec<CardData> = serde_json::from_str(&updated).unwrap();
+        assert_eq!(vec![card("pies", "dog")], cards);
+    }
+
+    #[test]
+    fn duplicate_card_is_found_by_position() {
+        let content = "[\n  {\"word\": \"kot\", \"translated\": \"cat\", \"explanation\": \"\", \"pronunciation\": \"\", \"sentence\": \"\"},\n  {\"word\": \"kot\", \"translated\": \"cat\", \"explanation\": \"\", \"pronunciation\": \"\", \"sentence\": \"\"}\n]\n";
+
+        let updated = replace_card(content, &at(1, card("kot", "cat")), &edited("kotek")).unwrap();
+
+        assert_eq!(
+            "[\n  {\"word\": \"kot\", \"translated\": \"cat\", \"explanation\": \"\", \"pronunciation\": \"\", \"sentence\": \"\"},\n  {\"word\": \"kotek\", \"translated\": \"cat\", \"explanation\": \"\", \"pronunciation\": \"\", \"sentence\": \"\", \"type\": \"noun\"}\n]\n",
+            updated
+        );
+    }
+
+    #[test]
+    fn missing_card_is_reported() {
+        let error = replace_card("[]", &at(0, card("kot", "cat")), &edited("kotek")).unwrap_err();
+        assert_eq!("card 'kot' not found", error.to_string());
+
+        let content = "[{\"word\":\"pies\",\"translated\":\"dog\",\"explanation\":\"\",\"pronunciation\":\"\",\"sentence\":\"\"}]";
+        let error =
+            replace_card(content, &at(0, card("kot", "cat")), &edited("kotek")).unwrap_err();
+        assert_eq!("card 'kot' not found", error.to_string());
+    }
+
+    #[test]
+    fn deck_file_is_updated() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("words.json");
+        write_deck(&path, &[card("kot", "cat"), card("pies", "dog")]).unwrap();
+
+        update_card(&path, &at(1, card("pies", "dog")), &edited("piesek")).unwrap();
+
+        let cards: Vec<CardData> =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(vec![card("kot", "cat"), edited("piesek")], cards);
+        assert_eq!(1, fs::read_dir(dir.path()).unwrap().count());
+    }
+}
@@ -0,0 +1,210 @@
+use cursive::traits::*;
+use cursive::views::{Dialog, EditView, LinearLayout, TextView};
+use cursive::Cursive;
+
+use crate::card::card_data::{split_tags, CardData};
+use crate::card::card_logic;
+
+pub const EDITOR_DIALOG: &str = "editor_dialog";
+pub const NEW_CARD_DIALOG: &str = "new_card_dialog";
+pub const DUPLICATE_DIALOG: &str = "duplicate_dialog";
+pub const SAVE_AS_DIALOG: &str = "save_as_dialog";
+const SAVE_AS_PATH: &str = "save_as_path";
+
+const LABEL_WIDTH: usize = 14;
+const FIELD_WIDTH: usize = 50;
+
+struct Field {
+    name: &'static str,
+    label: &'static str,
+    get: fn(&CardData) -> String,
+    set: fn(&mut CardData, String),
+}
+
+const FIELDS: &[Field] = &[
+    Field {
+        name: "editor_word",
+        label: "Word",
+        get: |card| card.word.clone(),
+        set: |card, value| card.word = value,
+    },
+    Field {
+        name: "editor_translated",
+        label: "Translation",
+        get: |card| card.translated.clone(),
+        set: |card, value| card.translated = value,
+    },
+    Field {
+        name: "editor_explanation",
+        label: "Explanation",
+        get: |card| card.explanation.clone(),
+        set: |card, value| card.explanation = value,
+    },
+    Field {
+        name: "editor_pronunciation",
+        label: "Pronunciation",
+        get: |card| card.pronunciation.clone(),
+        set: |card, value| card.pronunciation = value,
+    },
+    Field {
+        name: "editor_sentence",
+        label: "Sentence",
+        get: |card| card.sentence.clone(),
+        set: |card, value| card.sentence = value,
+    },
+    Field {
+        name: "editor_type",
+        label: "Type",
+        get: |card| card.card_type.clone().unwrap_or_default(),
+        set: |card, value| card.card_type = optional(value),
+    },
+    Field {
+        name: "editor_sentence_gap",
+        label: "Sentence gap",
+        get: |card| card.sentence_gap.clone().unwrap_or_default(),
+        set: |card, value| card.sentence_gap = optional(value),
+    },
+    Field {
+        name: "editor_gap_term",
+        label: "Gap term",
+        get: |card| card.gap_term.clone().unwrap_or_default(),
+        set: |card, value| card.gap_term = optional(value),
+    },
+    Field {
+        name: "editor_tags",
+        label: "Tags",
+        get: |card| card.tags.join(" "),
+        set: |card, value| card.tags = split_tags(&value),
+    },
+];
+
+fn optional(value: String) -> Option<String> {
+    Some(value).filter(|value| !value.is_empty())
+}
+
+pub fn show_editor(siv: &mut Cursive, card: &CardData) {
+    siv.add_layer(
+        Dialog::around(form(card, save))
+            .title("Edit card")
+            .button("Save", save)
+            .dismiss_button("Cancel")
+            .with_name(EDITOR_DIALOG),
+    );
+}
+
+pub fn show_new_card(siv: &mut Cursive) {
+    siv.add_layer(
+        Dialog::around(form(&CardData::default(), |s| add(s, true)))
+            .title("Add card")
+            .button("Add after current", |s| add(s, true))
+            .button("Add at end", |s| add(s, false))
+            .dismiss_button("Cancel")
+            .with_name(NEW_CARD_DIALOG),
+    );
+}
+
+fn form(card: &CardData, submit: fn(&mut Cursive)) -> LinearLayout {
+    let mut form = LinearLayout::vertical();
+    FIELDS.iter().for_each(|field| {
+        form.add_child(
+            LinearLayout::horizontal()
+                .child(TextView::new(field.label).fixed_width(LABEL_WIDTH))
+                .child(
+                    EditView::new()
+                        .content((field.get)(card))
+                        .on_submit(move |s, _| submit(s))
+                        .with_name(field.name)
+                        .fixed_width(FIELD_WIDTH),
+                ),
+        )
+    });
+    form
+}
+
+fn read_form(siv: &mut Cursive) -> Option<CardData> {
+    let mut card = CardData::default();
+    FIELDS.iter().for_each(|field| {
+        let value = siv
+            .find_name::<EditView>(field.name)
+            .map(|view| view.get_content().trim().to_string())
+            .unwrap_or_default();
+        (field.set)(&mut card, value);
+    });
+    if card.word.is_empty() || card.translated.is_empty() {
+        siv.add_layer(Dialog::info("Word and translation cannot be empty"));
+        return None;
+    }
+    Some(card)
+}
+
+fn save(siv: &mut Cursive) {
+    if let Some(card) = read_form(siv) {
+        siv.pop_layer();
+        card_logic::save_card(siv, card);
+    }
+}
+
+// A card with the same word needs a confirmation, the form stays open otherwise
+fn add(siv: &mut Cursive, after_current: bool) {
+    let card = match read_form(siv) {
+        Some(card) => card,
+        None => return,
+    };
+    let duplicate = match card_logic::find_word(siv, &card.word) {
+        Some(duplicate) => duplicate,
+        None => {
+            siv.pop_layer();
+            card_logic::add_card(siv, card, after_current);
+            return;
+        }
+    };
+
+    siv.add_layer(
+        Dialog::text(format!(
+            "'{}' is already in the deck ({} - {}). Add anyway?",
+            card.word, duplicate.word, duplicate.translated
+        ))
+        .title("Duplicate card")
+        .button("Add", move |s| {
+            s.pop_layer();
+            s.pop_layer();
+            card_logic::add_card(s, card.clone(), after_current);
+        })
+        .dismiss_button("Cancel")
+        .with_name(DUPLICATE_DIALOG),
+    );
+}
+
+// Decks read from stdin or from CSV have no JSON file to update
+pub fn show_save_as(siv: &mut Cursive) {
+    let path = EditView::new()
+        .on_submit(save_as)
+        .with_name(SAVE_AS_PATH)
+        .fixed_width(FIELD_WIDTH);
+    siv.add_layer(
+        Dialog::around(
+            LinearLayout::vertical()
+                .child(TextView::new("The deck has no JSON file, save it as:"))
+                .child(path),
+        )
+        .title("Save deck as")
+        .button("Save", |s| {
+            let path = s
+                .find_name::<EditView>(SAVE_AS_PATH)
+                .map(|view| view.get_content().to_string())
+                .unwrap_or_default();
+            save_as(s, &path);
+        })
+        .dismiss_button("Cancel")
+        .with_name(SAVE_AS_DIALOG),
+    );
+}
+
+fn save_as(siv: &mut Cursive, path: &str) {
+    let path = path.trim();
+    if path.is_empty() {
+        return;
+    }
+    siv.pop_layer();
+    card_logic::save_deck_as(siv, path);
+}
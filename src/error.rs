@@ -0,0 +1,233 @@
+use std::fmt;
+use std::io;
+
+// Errors of the library; the front-ends only show them
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Json(serde_json::Error),
+    Csv(csv::Error),
+    Anki(rusqlite::Error),
+    Zip(zip::result::ZipError),
+    Deck(DeckError),
+    Invalid(String),
+}
+
+// Problems of a deck file; loading stops at the first one, validation lists them all
+#[derive(Debug)]
+pub enum DeckError {
+    Io { path: String, error: io::Error },
+    Parse(Diagnostic),
+    Schema(Diagnostic),
+    Empty { path: String },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub path: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+    pub snippet: Option<String>,
+}
+
+const SNIPPET_WIDTH: usize = 60;
+
+impl Diagnostic {
+    pub fn new(path: &str, message: String) -> Self {
+        Self {
+            path: path.to_string(),
+            line: None,
+            column: None,
+            message,
+            snippet: None,
+        }
+    }
+
+    // Line and column are 1-based, the snippet shows the line with a caret under the column
+    pub fn at(mut self, content: &str, line: usize, column: Option<usize>) -> Self {
+        self.line = Some(line);
+        self.column = column;
+        self.snippet = content
+            .lines()
+            .nth(line.saturating_sub(1))
+            .map(|text| snippet(text, line, column));
+        self
+    }
+}
+
+fn snippet(text: &str, line: usize, column: Option<usize>) -> String {
+    let text = text.trim_end();
+    let chars = text.chars().count();
+    let column = column.map(|column| column.clamp(1, chars + 1));
+    let start = column.map_or(0, |column| column.saturating_sub(SNIPPET_WIDTH / 2 + 1));
+    let start = start.min(chars.saturating_sub(SNIPPET_WIDTH));
+    let mut shown: String = text.chars().skip(start).take(SNIPPET_WIDTH).collect();
+    let mut prefix_len = 0;
+    if start > 0 {
+        shown = format!("...{}", shown);
+        prefix_len = 3;
+    }
+    if start + SNIPPET_WIDTH < chars {
+        shown.push_str("...");
+    }
+
+    let gutter = " ".repeat(line.to_string().len());
+    let mut snippet = format!("{} | {}", line, shown);
+    if let Some(column) = column {
+        let caret: String = text
+            .chars()
+            .skip(start)
+            .take(column - 1 - start)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        snippet.push_str(&format!(
+            "\n{} | {}{}^",
+            gutter,
+            " ".repeat(prefix_len),
+            caret
+        ));
+    }
+    snippet
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path)?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+        }
+        if let Some(column) = self.column {
+            write!(f, ":{}", column)?;
+        }
+        write!(f, ": {}", self.message)?;
+        if let Some(snippet) = &self.snippet {
+            write!(f, "\n{}", snippet)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for DeckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeckError::Io { path, error } => write!(f, "{}: {}", path, error),
+            DeckError::Parse(diagnostic) | DeckError::Schema(diagnostic) => diagnostic.fmt(f),
+            DeckError::Empty { path } => write!(f, "{}: the deck has no cards", path),
+        }
+    }
+}
+
+impl std::error::Error for DeckError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DeckError::Io { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => e.fmt(f),
+            Error::Json(e) => e.fmt(f),
+            Error::Csv(e) => e.fmt(f),
+            Error::Anki(e) => e.fmt(f),
+            Error::Zip(e) => e.fmt(f),
+            Error::Deck(e) => e.fmt(f),
+            Error::Invalid(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Json(e) => Some(e),
+            Error::Csv(e) => Some(e),
+            Error::Anki(e) => Some(e),
+            Error::Zip(e) => Some(e),
+            Error::Deck(e) => Some(e),
+            Error::Invalid(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Json(e)
+    }
+}
+
+impl From<csv::Error> for Error {
+    fn from(e: csv::Error) -> Self {
+        Error::Csv(e)
+    }
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(e: rusqlite::Error) -> Self {
+        Error::Anki(e)
+    }
+}
+
+impl From<zip::result::ZipError> for Error {
+    fn from(e: zip::result::ZipError) -> Self {
+        Error::Zip(e)
+    }
+}
+
+impl From<DeckError> for Error {
+    fn from(e: DeckError) -> Self {
+        Error::Deck(e)
+    }
+}
+
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        Error::Invalid(message)
+    }
+}
+
+impl From<&str> for Error {
+    fn from(message: &str) -> Self {
+        Error::Invalid(message.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diagnostic_shows_line_with_caret() {
+        let content = "[\n  {\"word\": 1}\n]";
+        let diagnostic =
+            Diagnostic::new("words.json", "invalid type".to_string()).at(content, 2, Some(12));
+
+        assert_eq!(
+            "words.json:2:12: invalid type\n2 |   {\"word\": 1}\n  |            ^",
+            diagnostic.to_string()
+        );
+    }
+
+    #[test]
+    fn long_lines_are_cut_around_column() {
+        let line = format!("{}X{}", "a".repeat(100), "b".repeat(100));
+
+        let snippet = snippet(&line, 1, Some(101));
+
+        let lines: Vec<&str> = snippet.lines().collect();
+        assert_eq!(Some(3 + 30 + 4), lines[0].find('X'));
+        assert_eq!(lines[0].find('X'), lines[1].find('^'));
+        assert!(lines[0].starts_with("1 | ...") && lines[0].ends_with("..."));
+    }
+}
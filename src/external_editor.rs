@@ -0,0 +1,131 @@
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+
+use tempfile::Builder;
+
+use crate::card::card_data::CardData;
+
+const DEFAULT_EDITOR: &str = "vi";
+const HEADER: &str = "# Edit the card, save and quit the editor to apply the changes.\n\
+                      # Remove everything to cancel.\n";
+const ERROR_PREFIX: &str = "# Error: ";
+
+// Runs while the terminal is released by cursive; Ok(None) means the edit was cancelled
+pub fn edit_card(card: &CardData) -> Result<Option<CardData>, Box<dyn Error>> {
+    let mut file = Builder::new()
+        .prefix("worm-card-")
+        .suffix(".toml")
+        .tempfile()?;
+    write!(file, "{}{}", HEADER, toml::to_string(card)?)?;
+    file.flush()?;
+
+    loop {
+        run_editor(file.path())?;
+        let content = fs::read_to_string(file.path())?;
+        match parse_card(&content) {
+            Ok(card) => return Ok(card),
+            Err(e) => fs::write(file.path(), with_error(&content, &e))?,
+        }
+    }
+}
+
+fn run_editor(path: &Path) -> Result<(), Box<dyn Error>> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .ok()
+        .filter(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_EDITOR.to_string());
+    let mut args = editor.split_whitespace();
+    let program = args.next().unwrap_or(DEFAULT_EDITOR);
+
+    let status = Command::new(program)
+        .args(args)
+        .arg(path)
+        .status()
+        .map_err(|e| format!("Cannot run editor '{}': {}", editor, e))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("Editor '{}' failed: {}", editor, status).into())
+    }
+}
+
+fn parse_card(content: &str) -> Result<Option<CardData>, String> {
+    let is_empty = content
+        .lines()
+        .all(|line| line.trim().is_empty() || line.trim_start().starts_with('#'));
+    if is_empty {
+        return Ok(None);
+    }
+
+    let card: CardData = toml::from_str(content).map_err(|e| e.to_string())?;
+    if card.word.trim().is_empty() || card.translated.trim().is_empty() {
+        return Err("word and translated cannot be empty".to_string());
+    }
+    Ok(Some(card))
+}
+
+// Errors of the previous attempt are replaced
+fn with_error(content: &str, error: &str) -> String {
+    let errors: String = error
+        .lines()
+        .map(|line| format!("{}{}\n", ERROR_PREFIX, line))
+        .collect();
+    let content: String = content
+        .lines()
+        .filter(|line| !line.starts_with(ERROR_PREFIX))
+        .map(|line| format!("{}\n", line))
+        .collect();
+    format!("{}{}", errors, content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card() -> CardData {
+        CardData {
+            word: "kot".to_string(),
+            translated: "cat".to_string(),
+            card_type: Some("noun".to_string()),
+            ..CardData::default()
+        }
+    }
+
+    #[test]
+    fn card_survives_round_trip() {
+        let content = format!("{}{}", HEADER, toml::to_string(&card()).unwrap());
+
+        assert_eq!(Ok(Some(card())), parse_card(&content));
+    }
+
+    #[test]
+    fn empty_file_cancels_edit() {
+        assert_eq!(Ok(None), parse_card(HEADER));
+    }
+
+    #[test]
+    fn invalid_card_is_reported() {
+        assert_eq!(
+            Err("word and translated cannot be empty".to_string()),
+            parse_card("word = \"kot\"\ntranslated = \" \"\nexplanation = \"\"\npronunciation = \"\"\nsentence = \"\"\n")
+        );
+        assert!(parse_card("word = \"kot\"")
+            .unwrap_err()
+            .contains("missing field"));
+    }
+
+    #[test]
+    fn previous_errors_are_replaced() {
+        let content = with_error("word = 1\n", "first");
+
+        assert_eq!(
+            "# Error: second\nword = 1\n",
+            with_error(&content, "second")
+        );
+    }
+}
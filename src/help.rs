@@ -3,21 +3,27 @@ use cursive::traits::*;
 use cursive::views::{Dialog, LinearLayout, TextView};
 use cursive::Cursive;
 
+use crate::card::{card_data, card_logic};
 use crate::shortcuts;
 use crate::shortcuts::{DisplayEventInHelp, ShortcutData};
 
 pub const HELP_DIALOG: &str = "help_dialog";
 
 pub fn show_help(siv: &mut Cursive) {
-    if let Some(_) = siv.find_name::<Dialog>(HELP_DIALOG) {
+    if siv.find_name::<Dialog>(HELP_DIALOG).is_some() {
         return;
     }
 
+    let keymap = card_logic::keymap(siv);
     let mut layout =
         LinearLayout::vertical().child(TextView::new("Help").effect(Effect::Bold).fixed_height(2));
 
     shortcuts::ALL_SHORTCUTS.iter().for_each(|shortcut| {
-        let texts: Vec<String> = shortcut.event().iter().map(|e| e.help_text()).collect();
+        let texts: Vec<String> = keymap
+            .events(*shortcut)
+            .iter()
+            .map(|e| e.help_text())
+            .collect();
 
         layout.add_child(TextView::new(format!(
             "{:7}   {}",
@@ -25,5 +31,10 @@ pub fn show_help(siv: &mut Cursive) {
             shortcut.desc()
         )))
     });
+    layout.add_child(TextView::new(format!(
+        "{:7}   Count for the next shortcut, e.g. 25l / choice card: 1-{} pick answer",
+        "0-9",
+        card_data::CHOICES
+    )));
     siv.add_layer(Dialog::around(layout.scrollable()).with_name(HELP_DIALOG));
 }
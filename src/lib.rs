@@ -0,0 +1,27 @@
+pub mod answer;
+pub mod card;
+pub mod deck;
+pub mod error;
+pub mod scheduler;
+pub mod session;
+pub mod summary;
+pub mod xdg;
+
+#[cfg(feature = "tui")]
+pub mod browser;
+#[cfg(feature = "tui")]
+pub mod config;
+#[cfg(feature = "tui")]
+pub mod editor;
+#[cfg(feature = "tui")]
+pub mod external_editor;
+#[cfg(feature = "tui")]
+pub mod help;
+#[cfg(feature = "tui")]
+pub mod shortcuts;
+
+pub use crate::card::card_data::{CardData, CardSet};
+pub use crate::deck::{
+    read_card_set, read_cards_from_file, read_cards_from_stdin, Decks, StudyOptions,
+};
+pub use crate::error::Error;
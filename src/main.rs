@@ -1,46 +1,331 @@
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::error::Error;
-use std::fs::File;
-use std::io::{self, BufReader, Read};
-use std::path::Path;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::PathBuf;
+use std::process;
 use std::rc::Rc;
+use std::time::Instant;
 
-use clap::{App, Arg, ArgMatches};
+use chrono::Local;
+use clap::{value_t, values_t, App, Arg, ArgMatches, SubCommand};
 use cursive::event::Event;
+use cursive::views::Dialog;
+use cursive::Cursive;
 use log::info;
-use rand::seq::SliceRandom;
-use rand::thread_rng;
-use simplelog::{Config, LevelFilter, WriteLogger};
+use simplelog::{LevelFilter, WriteLogger};
 
-use card::card_logic;
+use worm::card::card_data::{CardData, CardSet, Mode};
+use worm::card::card_logic::{self, UserData};
+use worm::card::card_ui;
+use worm::card::filter::Filter;
+use worm::config::Config;
+use worm::deck::anki;
+use worm::deck::export::{write_cards, ExportFormat};
+use worm::deck::{
+    check_file, expand_inputs, read_card_set, read_cards_from_file, Decks, Format, LoadOptions,
+    Merge, StudyOptions,
+};
+use worm::external_editor;
+use worm::scheduler::progress::Progress;
+use worm::scheduler::{self, SchedulerKind};
+use worm::session::{self, SessionState};
+use worm::shortcuts::{self, ShortcutData};
+use worm::summary::Summary;
 
-use crate::card::card_data::{CardData, CardSet};
-use crate::card::card_ui;
-use crate::shortcuts::ShortcutData;
-
-mod card;
-mod help;
-mod shortcuts;
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
 
-fn main() -> Result<(), Box<dyn Error>> {
+fn run() -> Result<(), Box<dyn Error>> {
     let matches = parse_comman_line_args();
-    if let Some(file) = matches.value_of("debug") {
+    let sub_matches = match matches.subcommand() {
+        (_, Some(sub_matches)) => sub_matches,
+        _ => &matches,
+    };
+    if let Some(file) = sub_matches.value_of("debug") {
         set_up_logger(file);
     }
 
-    let input_file = matches.value_of("input");
-    let shuffle_cards = matches.is_present("shuffle");
-    let card_set = read_card_set(input_file, shuffle_cards)?;
+    match matches.subcommand() {
+        ("review", Some(review_matches)) => study(review_matches, SchedulerKind::Sm2),
+        ("import", Some(import_matches)) => import(import_matches),
+        ("export", Some(export_matches)) => export(export_matches),
+        ("validate", Some(validate_matches)) => validate(validate_matches),
+        _ => study(&matches, SchedulerKind::Linear),
+    }
+}
+
+fn study(matches: &ArgMatches, default_scheduler: SchedulerKind) -> Result<(), Box<dyn Error>> {
+    let config = Config::load(matches.value_of("config"))?;
+    let inputs: Vec<&str> = matches.values_of("input").into_iter().flatten().collect();
+    let decks = if inputs.is_empty() {
+        Decks::default()
+    } else {
+        Decks::load(&expand_inputs(&inputs)?)?
+    };
+    // options of a manifest go before the config, the command line before both
+    let options = StudyOptions {
+        shuffle: if matches.is_present("shuffle") || matches.is_present("no_shuffle") {
+            Some(matches.is_present("shuffle"))
+        } else {
+            None
+        },
+        mode: if matches.occurrences_of("mode") > 0 {
+            Some(value_t!(matches, "mode", Mode)?)
+        } else {
+            None
+        },
+        visibility: None,
+    };
+    let defaults = StudyOptions {
+        shuffle: config.shuffle,
+        mode: config.mode,
+        visibility: Some(config.visibility),
+    };
+    let scheduler_kind = if matches.occurrences_of("scheduler") > 0 {
+        value_t!(matches, "scheduler", SchedulerKind)?
+    } else {
+        default_scheduler
+    };
+    let leitner_frequencies = values_t!(matches, "leitner_frequencies", u32)?;
+    let today = Local::now().date_naive();
+
+    let load_options = load_options(matches)?;
+    let deck_files = decks.files();
+    let session_decks = decks.paths();
+
+    let scheduler = scheduler::create(scheduler_kind, &deck_files, leitner_frequencies, today)?;
+    let session = if decks.is_empty() || matches.is_present("fresh") {
+        SessionState::default()
+    } else {
+        SessionState::load_for_decks(&session_decks)?
+    };
+    let card_set = Rc::new(RefCell::new(read_card_set(
+        &decks,
+        &load_options,
+        options,
+        defaults,
+        scheduler,
+        &session,
+    )?));
+    if scheduler_kind != SchedulerKind::Linear && card_set.borrow().cards_len() == 0 {
+        println!("No cards due today in {}", card_set.borrow().name);
+        return Ok(());
+    }
+    info!(
+        "{} cards to study with {:?} scheduler",
+        card_set.borrow().cards_len(),
+        scheduler_kind
+    );
+
+    let json_decks = deck_files
+        .iter()
+        .filter(|path| load_options.format.unwrap_or_else(|| Format::detect(path)) == Format::Json)
+        .cloned()
+        .collect();
+    let started = Instant::now();
+    run_tui(&card_set, &config, json_decks, started);
+
+    card_set.borrow_mut().save_progress()?;
+    if !decks.is_empty() {
+        SessionState::of(&card_set.borrow()).save_for_decks(&session_decks)?;
+    }
+    if let Some(target) = matches.value_of("summary_json") {
+        Summary::of(&card_set.borrow(), started.elapsed()).write_json(target)?;
+    }
+    Ok(())
+}
+
+fn load_options(matches: &ArgMatches) -> Result<LoadOptions, Box<dyn Error>> {
+    Ok(LoadOptions {
+        format: match matches.value_of("format") {
+            Some(_) => Some(value_t!(matches, "format", Format)?),
+            None => None,
+        },
+        columns: matches
+            .values_of("columns")
+            .map(|columns| columns.map(String::from).collect()),
+        merge: value_t!(matches, "merge", Merge)?,
+        filter: Filter {
+            tags: values(matches, "tag"),
+            exclude_tags: values(matches, "exclude_tag"),
+            types: values(matches, "type"),
+            decks: values(matches, "deck"),
+        },
+    })
+}
+
+fn values(matches: &ArgMatches, name: &str) -> Vec<String> {
+    matches
+        .values_of(name)
+        .into_iter()
+        .flatten()
+        .map(String::from)
+        .collect()
+}
+
+fn import(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let input_file = matches.value_of("input").unwrap();
+    let mut mappings = HashMap::new();
+    for mapping in matches.values_of("map").into_iter().flatten() {
+        let (note_type, columns) = mapping
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid mapping '{}', expected NOTE_TYPE=COLUMNS", mapping))?;
+        mappings.insert(
+            note_type.trim().to_string(),
+            columns.split(',').map(String::from).collect::<Vec<_>>(),
+        );
+    }
+
+    let import = anki::import(input_file, &mappings)?;
+    import
+        .warnings
+        .iter()
+        .for_each(|warning| eprintln!("Warning: {}", warning));
+    let json = serde_json::to_string_pretty(&import.cards)?;
+    match matches.value_of("output") {
+        Some(output_file) => {
+            fs::write(output_file, json + "\n")?;
+            eprintln!("Imported {} cards to {}", import.cards.len(), output_file);
+        }
+        None => println!("{}", json),
+    }
+    Ok(())
+}
+
+fn export(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let input_file = matches.value_of("input").unwrap();
+    let output_file = matches.value_of("output");
+    let format = match matches.value_of("to") {
+        Some(_) => value_t!(matches, "to", ExportFormat)?,
+        None => output_file
+            .map(ExportFormat::detect)
+            .unwrap_or(ExportFormat::Anki),
+    };
+    let marked = if matches.is_present("marked") {
+        Some(session::marked_cards(input_file)?)
+    } else {
+        None
+    };
+    let max_ease = match matches.value_of("max_ease") {
+        Some(_) => Some(value_t!(matches, "max_ease", f64)?),
+        None => None,
+    };
+
+    let load_options = load_options(matches)?;
+    let mut cards = read_cards_from_file(input_file, &load_options)?;
+    cards
+        .iter_mut()
+        .for_each(|card| card.source = Some(PathBuf::from(input_file)));
+    let progress = Progress::load_for_deck(input_file)?;
+    let cards: Vec<&CardData> = cards
+        .iter()
+        .filter(|card| load_options.filter.matches(card))
+        .filter(|card| {
+            marked
+                .as_ref()
+                .is_none_or(|marked| marked.contains(&card.id()))
+        })
+        .filter(|card| {
+            max_ease.is_none_or(|max_ease| {
+                progress
+                    .get(card)
+                    .and_then(|progress| progress.sm2.as_ref())
+                    .is_some_and(|sm2| sm2.ease < max_ease)
+            })
+        })
+        .collect();
+
+    match output_file {
+        Some(output_file) => {
+            write_cards(&cards, format, BufWriter::new(File::create(output_file)?))?;
+            eprintln!("Exported {} cards to {}", cards.len(), output_file);
+        }
+        None => write_cards(&cards, format, io::stdout().lock())?,
+    }
+    Ok(())
+}
+
+fn validate(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let load_options = load_options(matches)?;
+    let mut problems = 0;
+    let mut paths: Vec<PathBuf> = vec![];
+    for input in matches.values_of("input").into_iter().flatten() {
+        match expand_inputs(&[input]) {
+            Ok(found) => found.into_iter().for_each(|path| {
+                if !paths.contains(&path) {
+                    paths.push(path);
+                }
+            }),
+            Err(e) => {
+                println!("{}", e);
+                problems += 1;
+            }
+        }
+    }
+    let (decks, errors) = Decks::check(&paths);
+    errors.iter().for_each(|error| println!("{}", error));
+    problems += errors.len();
+    for input_file in decks.files() {
+        match check_file(&input_file, &load_options) {
+            Ok(cards) => println!(
+                "{}: {} cards, no problems found",
+                input_file.display(),
+                cards.len()
+            ),
+            Err(errors) => {
+                errors.iter().for_each(|error| println!("{}", error));
+                problems += errors.len();
+            }
+        }
+    }
+    match problems {
+        0 => Ok(()),
+        1 => Err("1 problem found".into()),
+        _ => Err(format!("{} problems found", problems).into()),
+    }
+}
 
+fn run_tui(
+    card_set: &Rc<RefCell<CardSet>>,
+    config: &Config,
+    json_decks: Vec<PathBuf>,
+    started: Instant,
+) {
     info!("Setting up cursive");
     let mut siv = cursive::default();
-    siv.set_user_data(card_set.clone());
+    let keymap = Rc::new(config.keymap.clone());
     shortcuts::ALL_SHORTCUTS.iter().for_each(|shortcut| {
-        let shortcut = shortcut.clone();
-        shortcut.event().iter().for_each(|event| {
-            info!("..add callback for key {:?}: {}", event, shortcut.desc());
-            siv.add_global_callback(event.clone(), move |siv| shortcut.call(siv));
-        })
+        let shortcut = *shortcut;
+        keymap
+            .events(shortcut)
+            .iter()
+            .filter(|event| !matches!(event, Event::Char(c) if c.is_ascii_digit()))
+            .for_each(|event| {
+                info!("..add callback for key {:?}: {}", event, shortcut.desc());
+                siv.add_global_callback(event.clone(), move |siv| shortcut.call(siv));
+            })
+    });
+    ('0'..='9').for_each(|digit| {
+        let shortcut = keymap.shortcut(&Event::Char(digit));
+        siv.add_global_callback(digit, move |siv| {
+            shortcuts::call_or_count(siv, digit, shortcut)
+        });
+    });
+    siv.set_user_data(UserData {
+        card_set: card_set.clone(),
+        keymap,
+        count: None,
+        search: None,
+        deck: None,
+        json_decks,
+        external_edit: false,
+        started,
     });
 
     siv.set_on_pre_event(Event::WindowResize, |s| {
@@ -48,12 +333,37 @@ fn main() -> Result<(), Box<dyn Error>> {
         card_logic::refresh(s)
     });
 
-    siv.update_theme(|f| f.shadow = false);
+    siv.update_theme(|theme| config.apply_theme(theme));
     card_ui::setup_layout(&mut siv, &card_set.borrow());
     info!("Cursive set up! Starting");
     siv.run();
+    while card_logic::take_external_edit(&mut siv) {
+        siv = edit_externally(siv, card_set);
+        siv.run();
+    }
+}
 
-    Ok(())
+// Cursive gives the terminal back only when dropped, so its state is moved to a new instance;
+// the crossterm backend reads keys only while running, so all of them go to the editor
+fn edit_externally(mut siv: Cursive, card_set: &Rc<RefCell<CardSet>>) -> Cursive {
+    let dump = siv.dump();
+    drop(siv);
+    let edited = match card_set.borrow().get_current_card() {
+        Some(card) => external_editor::edit_card(card),
+        None => Ok(None),
+    };
+
+    let mut siv = cursive::default();
+    siv.restore(dump);
+    match edited {
+        Ok(Some(card)) => card_logic::save_card(&mut siv, card),
+        Ok(None) => card_logic::refresh(&mut siv),
+        Err(e) => {
+            card_logic::refresh(&mut siv);
+            siv.add_layer(Dialog::info(e.to_string()));
+        }
+    }
+    siv
 }
 
 fn parse_comman_line_args<'a>() -> ArgMatches<'a> {
@@ -63,21 +373,223 @@ fn parse_comman_line_args<'a>() -> ArgMatches<'a> {
         .arg(
             Arg::with_name("input")
                 .value_name("INPUT")
-                .help("JSON file with content; if not provided stdin will be used"),
+                .multiple(true)
+                .help("JSON, CSV or TSV files, .worm.toml manifests, directories or glob patterns with content; if not provided stdin will be used"),
+        )
+        .arg(
+            Arg::with_name("merge")
+                .long("merge")
+                .value_name("MERGE")
+                .possible_values(&["concat", "interleave"])
+                .default_value("concat")
+                .global(true)
+                .help("How cards of several decks are put together"),
+        )
+        .arg(
+            Arg::with_name("format")
+                .short("f")
+                .long("format")
+                .value_name("FORMAT")
+                .possible_values(&["json", "csv", "tsv"])
+                .global(true)
+                .help("Input format; detected from file extension by default, stdin is JSON"),
+        )
+        .arg(
+            Arg::with_name("columns")
+                .long("columns")
+                .value_name("COLUMNS")
+                .use_delimiter(true)
+                .global(true)
+                .help("Card fields of CSV/TSV columns, e.g. word,translated,-,sentence; header row is used by default"),
+        )
+        .arg(
+            Arg::with_name("tag")
+                .long("tag")
+                .value_name("TAG")
+                .multiple(true)
+                .number_of_values(1)
+                .global(true)
+                .help("Only cards with this tag; cards with any of the tags given are kept"),
+        )
+        .arg(
+            Arg::with_name("exclude_tag")
+                .long("exclude-tag")
+                .value_name("TAG")
+                .multiple(true)
+                .number_of_values(1)
+                .global(true)
+                .help("Leave out cards with this tag"),
+        )
+        .arg(
+            Arg::with_name("type")
+                .long("type")
+                .value_name("TYPE")
+                .multiple(true)
+                .number_of_values(1)
+                .global(true)
+                .help("Only cards of this type, e.g. noun"),
+        )
+        .arg(
+            Arg::with_name("deck")
+                .long("deck")
+                .value_name("DECK")
+                .multiple(true)
+                .number_of_values(1)
+                .global(true)
+                .help("Only cards of this deck, given by path or file name with or without extension"),
         )
         .arg(
             Arg::with_name("shuffle")
                 .short("s")
                 .long("shuffle")
+                .global(true)
                 .help("Shuffle input to create unique experience"),
         )
+        .arg(
+            Arg::with_name("no_shuffle")
+                .long("no-shuffle")
+                .conflicts_with("shuffle")
+                .global(true)
+                .help("Keep the order of the input even if the config says to shuffle"),
+        )
+        .arg(
+            Arg::with_name("mode")
+                .short("m")
+                .long("mode")
+                .value_name("MODE")
+                .possible_values(&["flip", "type", "cloze", "choice"])
+                .default_value("flip")
+                .global(true)
+                .help("Flip cards, type the translation, fill the gap in example sentence or pick the translation; overrides the config"),
+        )
+        .arg(
+            Arg::with_name("scheduler")
+                .long("scheduler")
+                .value_name("SCHEDULER")
+                .possible_values(&["linear", "sm2", "leitner"])
+                .default_value("linear")
+                .global(true)
+                .help("Which cards to study and in what order; sm2 and leitner keep progress next to the deck file (review defaults to sm2)"),
+        )
+        .arg(
+            Arg::with_name("leitner_frequencies")
+                .long("leitner-frequencies")
+                .value_name("DAYS")
+                .use_delimiter(true)
+                .default_value("1,2,4,8,16")
+                .global(true)
+                .help("Days between reviews of cards in each Leitner box"),
+        )
+        .arg(
+            Arg::with_name("fresh")
+                .long("fresh")
+                .global(true)
+                .help("Start from the first card instead of resuming the last session of the deck"),
+        )
+        .arg(
+            Arg::with_name("config")
+                .long("config")
+                .value_name("CONFIG_FILE")
+                .global(true)
+                .help("Config file with defaults, key bindings and colors; ~/.config/worm/config.toml by default"),
+        )
+        .arg(
+            Arg::with_name("summary_json")
+                .long("summary-json")
+                .value_name("FILE")
+                .global(true)
+                .help("Write the session summary as JSON to FILE, or to stdout when FILE is -"),
+        )
         .arg(
             Arg::with_name("debug")
                 .short("d")
                 .long("debug")
                 .value_name("DEBUG_FILE")
+                .global(true)
                 .help("Debug file path; turns on logging"),
         )
+        .subcommand(
+            SubCommand::with_name("review")
+                .about("Review cards due today; progress is kept next to the deck file")
+                .arg(
+                    Arg::with_name("input")
+                        .value_name("INPUT")
+                        .required(true)
+                        .multiple(true)
+                        .help("JSON, CSV or TSV files, .worm.toml manifests, directories or glob patterns with content"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("import")
+                .about("Convert an Anki package (.apkg or .colpkg) to a JSON deck")
+                .arg(
+                    Arg::with_name("input")
+                        .value_name("PACKAGE")
+                        .required(true)
+                        .help("Anki package file"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .value_name("OUTPUT")
+                        .help("JSON file to write; stdout by default"),
+                )
+                .arg(
+                    Arg::with_name("map")
+                        .long("map")
+                        .value_name("NOTE_TYPE=COLUMNS")
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help("Card fields of note type fields, e.g. 'Basic (and reversed card)=word,translated'; by default fields are matched by name"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("export")
+                .about("Write cards as Anki text (tab separated, with tags), CSV or JSON")
+                .arg(
+                    Arg::with_name("input")
+                        .value_name("INPUT")
+                        .required(true)
+                        .help("JSON, CSV or TSV file with content"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .value_name("OUTPUT")
+                        .help("File to write; stdout by default"),
+                )
+                .arg(
+                    Arg::with_name("to")
+                        .long("to")
+                        .value_name("FORMAT")
+                        .possible_values(&["anki", "csv", "json"])
+                        .help("Output format; detected from output file extension by default, anki otherwise"),
+                )
+                .arg(
+                    Arg::with_name("marked")
+                        .long("marked")
+                        .help("Only cards marked to be repeated when the deck was last studied"),
+                )
+                .arg(
+                    Arg::with_name("max_ease")
+                        .long("max-ease")
+                        .value_name("EASE")
+                        .help("Only cards reviewed with sm2 whose ease is below EASE, e.g. 2.0"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("validate")
+                .about("Check decks and list every problem found in them")
+                .arg(
+                    Arg::with_name("input")
+                        .value_name("INPUT")
+                        .required(true)
+                        .multiple(true)
+                        .help("JSON, CSV or TSV files, .worm.toml manifests, directories or glob patterns to check"),
+                ),
+        )
         .get_matches();
     matches
 }
@@ -85,50 +597,8 @@ fn parse_comman_line_args<'a>() -> ArgMatches<'a> {
 fn set_up_logger(debug_file: &str) {
     WriteLogger::init(
         LevelFilter::Info,
-        Config::default(),
+        simplelog::Config::default(),
         File::create(debug_file).unwrap(),
     )
     .unwrap();
 }
-
-fn read_card_set(
-    input_file: Option<&str>,
-    shuffle_cards: bool,
-) -> Result<Rc<RefCell<CardSet>>, Box<dyn Error>> {
-    Ok(Rc::new(RefCell::new(match input_file {
-        Some(path) => {
-            let cards = read_cards_from_file(path, shuffle_cards)?;
-            info!("Read set of {} cards from file: {}", cards.len(), path);
-            CardSet::new(path, cards)
-        }
-        None => {
-            info!("Reading from stdin");
-            let cards = read_cards_from_stdin(shuffle_cards)?;
-            info!("Read set of {} cards from stdin", cards.len());
-            CardSet::new("stdin", cards)
-        }
-    })))
-}
-
-fn read_cards_from_file<P: AsRef<Path>>(
-    path: P,
-    shuffle_cards: bool,
-) -> Result<Vec<CardData>, Box<dyn Error>> {
-    let file = File::open(path)?;
-    let reader = BufReader::new(file);
-    Ok(serde_json::from_reader(reader).map(|cards| shuffle(cards, shuffle_cards))?)
-}
-
-fn read_cards_from_stdin(shuffle_cards: bool) -> Result<Vec<CardData>, Box<dyn Error>> {
-    let mut buffer = String::new();
-    io::stdin().read_to_string(&mut buffer)?;
-    Ok(serde_json::from_str(&buffer).map(|cards| shuffle(cards, shuffle_cards))?)
-}
-
-fn shuffle(mut data: Vec<CardData>, shuffle_cards: bool) -> Vec<CardData> {
-    if shuffle_cards {
-        info!("shuffling card set");
-        data.shuffle(&mut thread_rng());
-    }
-    data
-}
@@ -0,0 +1,169 @@
+use chrono::{Duration, NaiveDate};
+
+use crate::card::card_data::{CardData, Grade};
+use crate::error::Error;
+use crate::scheduler::progress::Progress;
+use crate::scheduler::Scheduler;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct LeitnerState {
+    #[serde(rename = "box")]
+    pub box_nr: usize,
+    pub last_review: NaiveDate,
+}
+
+// frequencies[n] is the number of days between reviews of cards in box n
+#[derive(Debug)]
+pub struct Leitner {
+    progress: Progress,
+    frequencies: Vec<u32>,
+    today: NaiveDate,
+}
+
+impl Leitner {
+    pub fn new(progress: Progress, frequencies: Vec<u32>, today: NaiveDate) -> Self {
+        Self {
+            progress,
+            frequencies,
+            today,
+        }
+    }
+
+    fn last_box(&self) -> usize {
+        self.frequencies.len().saturating_sub(1)
+    }
+}
+
+impl Scheduler for Leitner {
+    fn is_due(&self, card: &CardData) -> bool {
+        match self.progress.get(card).and_then(|p| p.leitner.as_ref()) {
+            Some(state) => {
+                let box_nr = state.box_nr.min(self.last_box());
+                let frequency = self.frequencies.get(box_nr).copied().unwrap_or(1);
+                state.last_review + Duration::days(i64::from(frequency)) <= self.today
+            }
+            None => true,
+        }
+    }
+
+    fn record(&mut self, card: &CardData, grade: Grade) {
+        let last_box = self.last_box();
+        let today = self.today;
+        let progress = self.progress.get_mut(card);
+        let box_nr = progress
+            .leitner
+            .as_ref()
+            .map(|state| state.box_nr.min(last_box))
+            .unwrap_or(0);
+        let box_nr = match grade {
+            Grade::Again => 0,
+            Grade::Hard => box_nr,
+            Grade::Good | Grade::Easy => (box_nr + 1).min(last_box),
+        };
+        progress.leitner = Some(LeitnerState {
+            box_nr,
+            last_review: today,
+        });
+    }
+
+    fn save(&self) -> Result<(), Error> {
+        self.progress.save()
+    }
+
+    fn rename_card(&mut self, old: &CardData, new: &CardData) {
+        self.progress.rename(old, new);
+    }
+
+    fn stats(&self, card: &CardData) -> String {
+        match self.progress.get(card).and_then(|p| p.leitner.as_ref()) {
+            Some(state) => {
+                let box_nr = state.box_nr.min(self.last_box());
+                let frequency = self.frequencies.get(box_nr).copied().unwrap_or(1);
+                let due = state.last_review + Duration::days(i64::from(frequency));
+                format!("due {}, box {}", due, box_nr + 1)
+            }
+            None => "new".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2020, 11, 1).unwrap()
+    }
+
+    fn card() -> CardData {
+        serde_json::from_str(
+            r#"{"word": "kot", "translated": "cat", "explanation": "", "pronunciation": "", "sentence": ""}"#,
+        )
+        .unwrap()
+    }
+
+    fn leitner(today: NaiveDate) -> Leitner {
+        let progress = Progress::load_for_deck("/nonexistent/deck.json").unwrap();
+        Leitner::new(progress, vec![1, 3, 7], today)
+    }
+
+    fn box_of(leitner: &Leitner, card: &CardData) -> usize {
+        leitner
+            .progress
+            .get(card)
+            .and_then(|p| p.leitner.as_ref())
+            .map(|state| state.box_nr)
+            .unwrap()
+    }
+
+    #[test]
+    fn new_card_is_due() {
+        assert!(leitner(today()).is_due(&card()));
+    }
+
+    #[test]
+    fn stats_show_due_date_and_box() {
+        let mut leitner = leitner(today());
+        assert_eq!("new", leitner.stats(&card()));
+
+        leitner.record(&card(), Grade::Good);
+
+        assert_eq!("due 2020-11-04, box 2", leitner.stats(&card()));
+    }
+
+    #[test]
+    fn good_answer_moves_card_to_next_box() {
+        let mut leitner = leitner(today());
+
+        leitner.record(&card(), Grade::Good);
+        assert_eq!(1, box_of(&leitner, &card()));
+
+        leitner.record(&card(), Grade::Easy);
+        leitner.record(&card(), Grade::Good);
+        assert_eq!(2, box_of(&leitner, &card()));
+    }
+
+    #[test]
+    fn hard_answer_keeps_box_and_again_resets_it() {
+        let mut leitner = leitner(today());
+        leitner.record(&card(), Grade::Good);
+
+        leitner.record(&card(), Grade::Hard);
+        assert_eq!(1, box_of(&leitner, &card()));
+
+        leitner.record(&card(), Grade::Again);
+        assert_eq!(0, box_of(&leitner, &card()));
+    }
+
+    #[test]
+    fn card_is_due_after_box_frequency() {
+        let mut leitner = leitner(today());
+        leitner.record(&card(), Grade::Good);
+
+        leitner.today = today() + Duration::days(2);
+        assert!(!leitner.is_due(&card()));
+
+        leitner.today = today() + Duration::days(3);
+        assert!(leitner.is_due(&card()));
+    }
+}
@@ -0,0 +1,84 @@
+use std::fmt::Debug;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use chrono::NaiveDate;
+
+use crate::card::card_data::{CardData, Grade};
+use crate::error::Error;
+use crate::scheduler::leitner::Leitner;
+use crate::scheduler::progress::Progress;
+use crate::scheduler::sm2::Sm2;
+
+pub mod leitner;
+pub mod progress;
+pub mod sm2;
+
+// Schedulers only select the due cards of a round, the round is then
+// presented in order like without a scheduler
+pub trait Scheduler: Debug {
+    fn is_due(&self, card: &CardData) -> bool;
+
+    fn record(&mut self, card: &CardData, grade: Grade);
+
+    fn save(&self) -> Result<(), Error>;
+
+    // Called when an edit changes the id of a card
+    fn rename_card(&mut self, _old: &CardData, _new: &CardData) {}
+
+    // Short summary of the scheduling state of a card, shown in the card browser
+    fn stats(&self, _card: &CardData) -> String {
+        String::new()
+    }
+}
+
+#[derive(Debug)]
+pub struct Linear;
+
+impl Scheduler for Linear {
+    fn is_due(&self, _card: &CardData) -> bool {
+        true
+    }
+
+    fn record(&mut self, _card: &CardData, _grade: Grade) {}
+
+    fn save(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SchedulerKind {
+    Linear,
+    Sm2,
+    Leitner,
+}
+
+impl FromStr for SchedulerKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "linear" => Ok(SchedulerKind::Linear),
+            "sm2" => Ok(SchedulerKind::Sm2),
+            "leitner" => Ok(SchedulerKind::Leitner),
+            _ => Err(format!("Unknown scheduler: {}", s)),
+        }
+    }
+}
+
+pub fn create(
+    kind: SchedulerKind,
+    decks: &[PathBuf],
+    leitner_frequencies: Vec<u32>,
+    today: NaiveDate,
+) -> Result<Box<dyn Scheduler>, Error> {
+    let progress = match kind {
+        SchedulerKind::Linear => return Ok(Box::new(Linear)),
+        _ => Progress::load_for_decks(decks)?,
+    };
+    Ok(match kind {
+        SchedulerKind::Leitner => Box::new(Leitner::new(progress, leitner_frequencies, today)),
+        _ => Box::new(Sm2::new(progress, today)),
+    })
+}
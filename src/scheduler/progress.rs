@@ -0,0 +1,236 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use log::info;
+
+use crate::card::card_data::CardData;
+use crate::error::Error;
+use crate::scheduler::leitner::LeitnerState;
+use crate::scheduler::sm2::Sm2State;
+
+const PROGRESS_EXTENSION: &str = "progress.json";
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct CardProgress {
+    #[serde(flatten, default, skip_serializing_if = "Option::is_none")]
+    pub sm2: Option<Sm2State>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub leitner: Option<LeitnerState>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default)]
+struct ProgressData {
+    cards: BTreeMap<String, CardProgress>,
+}
+
+// Merged decks keep their progress next to each deck; cards find theirs by their source
+#[derive(Debug)]
+pub struct Progress {
+    decks: Vec<DeckProgress>,
+}
+
+#[derive(Debug)]
+struct DeckProgress {
+    deck: PathBuf,
+    path: PathBuf,
+    data: ProgressData,
+}
+
+impl Progress {
+    pub fn load_for_deck<P: AsRef<Path>>(deck: P) -> Result<Self, Error> {
+        Self::load_for_decks(&[deck.as_ref().to_path_buf()])
+    }
+
+    pub fn load_for_decks(decks: &[PathBuf]) -> Result<Self, Error> {
+        if decks.is_empty() {
+            return Err("Progress can be kept only for decks read from a file".into());
+        }
+        let decks = decks
+            .iter()
+            .map(|deck| {
+                let path = progress_path(deck);
+                let data = match File::open(&path) {
+                    Ok(file) => serde_json::from_reader(BufReader::new(file))?,
+                    Err(e) if e.kind() == ErrorKind::NotFound => {
+                        info!("No progress file at {}, starting fresh", path.display());
+                        ProgressData::default()
+                    }
+                    Err(e) => return Err(e.into()),
+                };
+                Ok(DeckProgress {
+                    deck: deck.clone(),
+                    path,
+                    data,
+                })
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(Self { decks })
+    }
+
+    // Cards without a known deck belong to the first one
+    fn deck_of(&self, card: &CardData) -> usize {
+        card.source
+            .as_ref()
+            .and_then(|source| self.decks.iter().position(|deck| deck.deck == *source))
+            .unwrap_or(0)
+    }
+
+    pub fn get(&self, card: &CardData) -> Option<&CardProgress> {
+        self.decks[self.deck_of(card)].data.cards.get(&card.id())
+    }
+
+    pub fn get_mut(&mut self, card: &CardData) -> &mut CardProgress {
+        let deck = self.deck_of(card);
+        self.decks[deck].data.cards.entry(card.id()).or_default()
+    }
+
+    // An edited card gets a new id, its progress goes with it
+    pub fn rename(&mut self, old: &CardData, new: &CardData) {
+        let old_id = old.id();
+        let new_id = new.id();
+        if old_id == new_id {
+            return;
+        }
+        let deck = self.deck_of(old);
+        if let Some(progress) = self.decks[deck].data.cards.remove(&old_id) {
+            let deck = self.deck_of(new);
+            self.decks[deck].data.cards.insert(new_id, progress);
+        }
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        for deck in &self.decks {
+            let tmp_path = deck.path.with_extension("tmp");
+            let mut writer = BufWriter::new(File::create(&tmp_path)?);
+            serde_json::to_writer_pretty(&mut writer, &deck.data)?;
+            writer.flush()?;
+            fs::rename(&tmp_path, &deck.path)?;
+            info!("Saved progress to {}", deck.path.display());
+        }
+        Ok(())
+    }
+}
+
+pub fn is_progress_file(path: &Path) -> bool {
+    path.to_string_lossy()
+        .ends_with(&format!(".{}", PROGRESS_EXTENSION))
+}
+
+// The extension stays in the name, words.csv and words.json keep separate progress
+fn progress_path(deck: &Path) -> PathBuf {
+    let name = deck
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    deck.with_file_name(format!("{}.{}", name, PROGRESS_EXTENSION))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    #[test]
+    fn progress_file_lives_next_to_deck() {
+        assert_eq!(
+            PathBuf::from("decks/animals.json.progress.json"),
+            progress_path(Path::new("decks/animals.json"))
+        );
+        assert_ne!(
+            progress_path(Path::new("decks/animals.csv")),
+            progress_path(Path::new("decks/animals.json"))
+        );
+    }
+
+    #[test]
+    fn progress_file_for_deck_without_extension() {
+        assert_eq!(
+            PathBuf::from("animals.progress.json"),
+            progress_path(Path::new("animals"))
+        );
+    }
+
+    #[test]
+    fn progress_of_merged_decks_is_kept_per_deck() {
+        let dir = tempfile::tempdir().unwrap();
+        let decks = vec![dir.path().join("a.json"), dir.path().join("b.json")];
+        let card = |word: &str, source: &PathBuf| CardData {
+            word: word.to_string(),
+            source: Some(source.clone()),
+            ..CardData::default()
+        };
+
+        let mut progress = Progress::load_for_decks(&decks).unwrap();
+        progress.get_mut(&card("kot", &decks[1])).leitner = Some(LeitnerState {
+            box_nr: 1,
+            last_review: NaiveDate::from_ymd_opt(2020, 11, 2).unwrap(),
+        });
+        progress.save().unwrap();
+
+        let progress = Progress::load_for_deck(&decks[1]).unwrap();
+        assert!(progress.get(&card("kot", &decks[1])).is_some());
+        let progress = Progress::load_for_deck(&decks[0]).unwrap();
+        assert!(progress.get(&card("kot", &decks[0])).is_none());
+    }
+
+    #[test]
+    fn progress_follows_edited_card() {
+        let dir = tempfile::tempdir().unwrap();
+        let deck = dir.path().join("a.json");
+        let card = |word: &str| CardData {
+            word: word.to_string(),
+            translated: "cat".to_string(),
+            source: Some(deck.clone()),
+            ..CardData::default()
+        };
+        let mut progress = Progress::load_for_deck(&deck).unwrap();
+        progress.get_mut(&card("kto")).leitner = Some(LeitnerState {
+            box_nr: 2,
+            last_review: NaiveDate::from_ymd_opt(2020, 11, 2).unwrap(),
+        });
+
+        progress.rename(&card("kto"), &card("kot"));
+
+        assert!(progress.get(&card("kto")).is_none());
+        assert_eq!(
+            2,
+            progress
+                .get(&card("kot"))
+                .unwrap()
+                .leitner
+                .as_ref()
+                .unwrap()
+                .box_nr
+        );
+    }
+
+    #[test]
+    fn reads_sm2_only_progress() {
+        let progress: CardProgress = serde_json::from_str(
+            r#"{"ease": 2.5, "interval": 1, "repetitions": 1, "due": "2020-11-02"}"#,
+        )
+        .unwrap();
+
+        assert_eq!(1, progress.sm2.unwrap().repetitions);
+        assert_eq!(None, progress.leitner);
+    }
+
+    #[test]
+    fn reads_leitner_only_progress() {
+        let progress: CardProgress =
+            serde_json::from_str(r#"{"leitner": {"box": 2, "last_review": "2020-11-02"}}"#)
+                .unwrap();
+
+        assert_eq!(None, progress.sm2);
+        assert_eq!(
+            Some(LeitnerState {
+                box_nr: 2,
+                last_review: NaiveDate::from_ymd_opt(2020, 11, 2).unwrap(),
+            }),
+            progress.leitner
+        );
+    }
+}
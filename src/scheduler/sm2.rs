@@ -0,0 +1,175 @@
+use chrono::{Duration, NaiveDate};
+
+use crate::card::card_data::{CardData, Grade};
+use crate::error::Error;
+use crate::scheduler::progress::Progress;
+use crate::scheduler::Scheduler;
+
+const INITIAL_EASE: f64 = 2.5;
+const MIN_EASE: f64 = 1.3;
+
+pub fn quality(grade: Grade) -> u8 {
+    match grade {
+        Grade::Again => 1,
+        Grade::Hard => 3,
+        Grade::Good => 4,
+        Grade::Easy => 5,
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct Sm2State {
+    pub ease: f64,
+    pub interval: u32,
+    pub repetitions: u32,
+    pub due: NaiveDate,
+}
+
+impl Sm2State {
+    pub fn new(today: NaiveDate) -> Self {
+        Self {
+            ease: INITIAL_EASE,
+            interval: 0,
+            repetitions: 0,
+            due: today,
+        }
+    }
+
+    pub fn is_due(&self, today: NaiveDate) -> bool {
+        self.due <= today
+    }
+
+    // quality is SM-2's 0-5 answer grade; anything below 3 counts as a lapse
+    pub fn review(&mut self, quality: u8, today: NaiveDate) {
+        let quality = quality.min(5);
+        if quality >= 3 {
+            self.interval = match self.repetitions {
+                0 => 1,
+                1 => 6,
+                _ => (f64::from(self.interval) * self.ease).round() as u32,
+            };
+            self.repetitions += 1;
+        } else {
+            self.interval = 1;
+            self.repetitions = 0;
+        }
+
+        let penalty = f64::from(5 - quality);
+        self.ease = (self.ease + 0.1 - penalty * (0.08 + penalty * 0.02)).max(MIN_EASE);
+        self.due = today + Duration::days(i64::from(self.interval));
+    }
+}
+
+#[derive(Debug)]
+pub struct Sm2 {
+    progress: Progress,
+    today: NaiveDate,
+}
+
+impl Sm2 {
+    pub fn new(progress: Progress, today: NaiveDate) -> Self {
+        Self { progress, today }
+    }
+}
+
+impl Scheduler for Sm2 {
+    fn is_due(&self, card: &CardData) -> bool {
+        self.progress
+            .get(card)
+            .and_then(|progress| progress.sm2.as_ref())
+            .map(|state| state.is_due(self.today))
+            .unwrap_or(true)
+    }
+
+    fn record(&mut self, card: &CardData, grade: Grade) {
+        let today = self.today;
+        self.progress
+            .get_mut(card)
+            .sm2
+            .get_or_insert_with(|| Sm2State::new(today))
+            .review(quality(grade), today);
+    }
+
+    fn save(&self) -> Result<(), Error> {
+        self.progress.save()
+    }
+
+    fn rename_card(&mut self, old: &CardData, new: &CardData) {
+        self.progress.rename(old, new);
+    }
+
+    fn stats(&self, card: &CardData) -> String {
+        match self
+            .progress
+            .get(card)
+            .and_then(|progress| progress.sm2.as_ref())
+        {
+            Some(state) => format!("due {}, ease {:.2}", state.due, state.ease),
+            None => "new".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2020, 11, 1).unwrap()
+    }
+
+    #[test]
+    fn new_card_is_due_today() {
+        let state = Sm2State::new(today());
+
+        assert!(state.is_due(today()));
+    }
+
+    #[test]
+    fn successful_reviews_grow_interval() {
+        let mut state = Sm2State::new(today());
+
+        state.review(quality(Grade::Good), today());
+        assert_eq!(1, state.interval);
+        assert_eq!(today() + Duration::days(1), state.due);
+        assert!(!state.is_due(today()));
+
+        state.review(quality(Grade::Good), today());
+        assert_eq!(6, state.interval);
+
+        state.review(quality(Grade::Good), today());
+        assert_eq!(15, state.interval);
+        assert_eq!(3, state.repetitions);
+    }
+
+    #[test]
+    fn failed_review_resets_repetitions_and_lowers_ease() {
+        let mut state = Sm2State::new(today());
+        state.review(quality(Grade::Good), today());
+        state.review(quality(Grade::Good), today());
+
+        state.review(quality(Grade::Again), today());
+
+        assert_eq!(0, state.repetitions);
+        assert_eq!(1, state.interval);
+        assert!(state.ease < INITIAL_EASE);
+    }
+
+    #[test]
+    fn ease_never_drops_below_minimum() {
+        let mut state = Sm2State::new(today());
+
+        (0..20).for_each(|_| state.review(0, today()));
+
+        assert!((state.ease - MIN_EASE).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn perfect_answer_raises_ease() {
+        let mut state = Sm2State::new(today());
+
+        state.review(quality(Grade::Easy), today());
+
+        assert!(state.ease > INITIAL_EASE);
+    }
+}
@@ -0,0 +1,337 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use log::info;
+
+use crate::card::card_data::{fnv1a, CardData, CardSet, Visibility};
+use crate::error::Error;
+use crate::xdg;
+
+const SESSIONS_DIR: &str = "sessions";
+
+// What is left of the last session of a deck; kept under $XDG_STATE_HOME.
+// Cards are kept by id, so the session survives edits of the deck.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct SessionState {
+    pub order: Vec<String>,
+    pub current: Option<String>,
+    pub round_nr: usize,
+    pub round: Vec<String>,
+    pub finished: bool,
+    pub reversed: bool,
+    pub visibility: Option<Visibility>,
+    pub marked: Vec<String>,
+}
+
+impl SessionState {
+    pub fn of(card_set: &CardSet) -> Self {
+        Self {
+            order: card_set.cards().iter().map(CardData::id).collect(),
+            current: card_set.get_current_card().map(CardData::id),
+            round_nr: card_set.round_nr(),
+            round: card_set.round_cards().map(CardData::id).collect(),
+            finished: card_set.is_round_finished(),
+            reversed: card_set.is_reversed(),
+            visibility: Some(card_set.get_visibility()),
+            marked: card_set.marked_cards().map(CardData::id).collect(),
+        }
+    }
+
+    // A finished session is not resumed, only its view toggles and marks are kept
+    pub fn is_resumable(&self) -> bool {
+        !self.finished && self.current.is_some()
+    }
+
+    // Cards missing from the last session go last
+    pub fn restore_order(&self, mut cards: Vec<CardData>) -> Vec<CardData> {
+        let positions: HashMap<&str, usize> = self
+            .order
+            .iter()
+            .enumerate()
+            .map(|(position, id)| (id.as_str(), position))
+            .collect();
+        cards.sort_by_cached_key(|card| {
+            positions
+                .get(card.id().as_str())
+                .copied()
+                .unwrap_or(usize::MAX)
+        });
+        cards
+    }
+
+    pub fn restore(&self, mut card_set: CardSet) -> CardSet {
+        if let Some(visibility) = self.visibility {
+            card_set = card_set.visibility(visibility);
+        }
+        card_set = card_set.mark_cards(&self.marked);
+        match &self.current {
+            Some(current) if self.is_resumable() => card_set
+                .resume_round(self.round_nr, &self.round)
+                .resume_at(current, self.reversed),
+            _ => card_set,
+        }
+    }
+
+    pub fn load_for_decks(decks: &[PathBuf]) -> Result<Self, Error> {
+        match session_path(decks) {
+            Some(path) => Self::load(&path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    pub fn save_for_decks(&self, decks: &[PathBuf]) -> Result<(), Error> {
+        match session_path(decks) {
+            Some(path) => self.save(&path),
+            None => {
+                info!("No state directory, session not saved");
+                Ok(())
+            }
+        }
+    }
+
+    fn load(path: &Path) -> Result<Self, Error> {
+        match File::open(path) {
+            Ok(file) => Ok(serde_json::from_reader(BufReader::new(file))
+                .map_err(|e| format!("Cannot read session {}: {}", path.display(), e))?),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn save(&self, path: &Path) -> Result<(), Error> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp_path = path.with_extension("tmp");
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()?;
+        fs::rename(&tmp_path, path)?;
+        info!("Saved session to {}", path.display());
+        Ok(())
+    }
+}
+
+#[derive(serde::Deserialize, Debug, Default)]
+#[serde(default)]
+struct Marks {
+    marked: Vec<String>,
+}
+
+// Ids of the cards marked to be repeated when the deck was last studied;
+// only reads the session, e.g. to export the hard words
+pub fn marked_cards<P: AsRef<Path>>(deck: P) -> Result<Vec<String>, Error> {
+    match session_path(&[deck.as_ref().to_path_buf()]) {
+        Some(path) => read_marks(&path),
+        None => Ok(vec![]),
+    }
+}
+
+fn read_marks(path: &Path) -> Result<Vec<String>, Error> {
+    match File::open(path) {
+        Ok(file) => {
+            let marks: Marks = serde_json::from_reader(BufReader::new(file))
+                .map_err(|e| format!("Cannot read session {}: {}", path.display(), e))?;
+            Ok(marks.marked)
+        }
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(vec![]),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn session_path(decks: &[PathBuf]) -> Option<PathBuf> {
+    xdg::state_dir().map(|dir| session_path_in(&dir, decks))
+}
+
+// Decks with the same name in different directories get separate sessions,
+// and so does each set of merged decks
+fn session_path_in(dir: &Path, decks: &[PathBuf]) -> PathBuf {
+    let decks: Vec<String> = decks
+        .iter()
+        .map(|deck| fs::canonicalize(deck).unwrap_or_else(|_| deck.clone()))
+        .map(|deck| deck.to_string_lossy().into_owned())
+        .collect();
+    let mut stem = decks
+        .first()
+        .and_then(|deck| Path::new(deck).file_stem())
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    if decks.len() > 1 {
+        stem = format!("{}+{}", stem, decks.len() - 1);
+    }
+    let hash = fnv1a(decks.join("\n").bytes());
+    dir.join(SESSIONS_DIR)
+        .join(format!("{}-{:016x}.json", stem, hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cards(words: &[&str]) -> Vec<CardData> {
+        words
+            .iter()
+            .map(|word| CardData {
+                word: word.to_string(),
+                translated: word.to_uppercase(),
+                ..CardData::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn missing_session_is_empty() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let state = SessionState::load(&dir.path().join("none.json")).unwrap();
+
+        assert_eq!(SessionState::default(), state);
+    }
+
+    #[test]
+    fn session_is_saved_and_loaded() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = session_path_in(dir.path(), &[PathBuf::from("words.json")]);
+        let state = SessionState {
+            marked: vec!["c33d0e8b6f5111da".to_string()],
+            ..SessionState::default()
+        };
+
+        state.save(&path).unwrap();
+
+        assert_eq!(state, SessionState::load(&path).unwrap());
+    }
+
+    #[test]
+    fn marks_are_read_from_the_last_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("words.json");
+        let state = SessionState {
+            marked: vec!["c33d0e8b6f5111da".to_string()],
+            current: Some("0a1b2c3d4e5f6071".to_string()),
+            ..SessionState::default()
+        };
+        state.save(&path).unwrap();
+
+        assert_eq!(state.marked, read_marks(&path).unwrap());
+        assert!(read_marks(&dir.path().join("none.json"))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn session_is_resumed_at_the_same_card() {
+        let mut card_set = CardSet::new("words", cards(&["a", "b", "c"]));
+        card_set.next_card();
+        card_set.next_card();
+        card_set.toggle_repeat_card();
+        card_set.reverse_current_card();
+        card_set.toggle_show_title();
+        let state = SessionState::of(&card_set);
+
+        let resumed = state.restore(CardSet::new("words", cards(&["a", "b", "c"])));
+
+        assert_eq!(2, resumed.current_card);
+        assert!(resumed.is_reversed());
+        assert!(resumed.repeat_current_card());
+        assert_eq!(card_set.get_visibility(), resumed.get_visibility());
+        assert_eq!(None, resumed.get_title());
+    }
+
+    #[test]
+    fn session_is_resumed_in_a_later_round() {
+        let mut card_set = CardSet::new("words", cards(&["a", "b", "c", "d"]));
+        card_set.toggle_repeat_card();
+        card_set.next_card();
+        card_set.next_card();
+        card_set.toggle_repeat_card();
+        card_set.next_card();
+        card_set.toggle_repeat_card();
+        card_set.next_card();
+        card_set.start_next_round();
+        card_set.next_card();
+        card_set.toggle_repeat_card();
+        let state = SessionState::of(&card_set);
+
+        let mut resumed = state.restore(CardSet::new("words", cards(&["a", "b", "c", "d"])));
+
+        assert_eq!(2, resumed.round_nr());
+        assert_eq!(3, resumed.cards_len());
+        assert_eq!(1, resumed.current_card);
+        assert_eq!("c", resumed.get_current_card().unwrap().word);
+        assert!(resumed.repeat_current_card());
+        assert_eq!(1, resumed.cards_to_repeat());
+        resumed.next_card();
+        resumed.next_card();
+        assert!(resumed.start_next_round());
+        assert_eq!(1, resumed.cards_len());
+    }
+
+    #[test]
+    fn finished_session_starts_over() {
+        let mut card_set = CardSet::new("words", cards(&["a", "b"]));
+        card_set.next_card();
+        card_set.toggle_repeat_card();
+        card_set.next_card();
+        let state = SessionState::of(&card_set);
+
+        let resumed = state.restore(CardSet::new("words", cards(&["a", "b"])));
+
+        assert!(!state.is_resumable());
+        assert_eq!(0, resumed.current_card);
+        assert_eq!(1, resumed.cards_to_repeat());
+    }
+
+    #[test]
+    fn order_is_restored() {
+        let state = SessionState {
+            order: cards(&["c", "a", "b"]).iter().map(CardData::id).collect(),
+            ..SessionState::default()
+        };
+
+        let restored = state.restore_order(cards(&["a", "b", "new", "c"]));
+
+        let words: Vec<&str> = restored.iter().map(|card| card.word.as_str()).collect();
+        assert_eq!(vec!["c", "a", "b", "new"], words);
+    }
+
+    #[test]
+    fn decks_with_same_name_have_separate_sessions() {
+        let dir = Path::new("/state");
+
+        let first = session_path_in(dir, &[PathBuf::from("/a/words.json")]);
+        let second = session_path_in(dir, &[PathBuf::from("/b/words.json")]);
+
+        assert_ne!(first, second);
+        assert!(first.starts_with("/state/sessions"));
+        assert!(first
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .starts_with("words-"));
+    }
+
+    #[test]
+    fn merged_decks_have_their_own_session() {
+        let dir = Path::new("/state");
+        let single = session_path_in(dir, &[PathBuf::from("/a/words.json")]);
+
+        let merged = session_path_in(
+            dir,
+            &[
+                PathBuf::from("/a/words.json"),
+                PathBuf::from("/a/verbs.json"),
+            ],
+        );
+
+        assert_ne!(single, merged);
+        assert!(merged
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .starts_with("words+1-"));
+    }
+}
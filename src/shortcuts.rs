@@ -1,16 +1,36 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
 use cursive::Cursive;
-use cursive::event::Event;
-use cursive::views::Dialog;
+use cursive::event::{Event, Key};
 
+use crate::card::card_data::{Grade, CHOICES};
 use crate::card::card_logic;
 use crate::help;
 
-pub const ALL_SHORTCUTS: &'static [Shortcut] = &[
+pub const ALL_SHORTCUTS: &[Shortcut] = &[
     Shortcut::Quit,
     Shortcut::ReverseCard,
     Shortcut::PrevCard,
     Shortcut::NextCard,
+    Shortcut::FirstCard,
+    Shortcut::LastCard,
+    Shortcut::PageBack,
+    Shortcut::PageForward,
+    Shortcut::GoToCard,
+    Shortcut::Search,
+    Shortcut::SearchNext,
+    Shortcut::SearchPrev,
+    Shortcut::Filter,
+    Shortcut::Browser,
+    Shortcut::EditCard,
+    Shortcut::EditCardExternally,
+    Shortcut::AddCard,
     Shortcut::MarkToRepeat,
+    Shortcut::GradeAgain,
+    Shortcut::GradeHard,
+    Shortcut::GradeGood,
+    Shortcut::GradeEasy,
     Shortcut::TogglePronunciationVisibility,
     Shortcut::ToggleDescriptionVisibility,
     Shortcut::ToggleExampleVisibility,
@@ -20,13 +40,30 @@ pub const ALL_SHORTCUTS: &'static [Shortcut] = &[
     Shortcut::Help,
 ];
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Shortcut {
     Quit,
     ReverseCard,
     PrevCard,
     NextCard,
+    FirstCard,
+    LastCard,
+    PageBack,
+    PageForward,
+    GoToCard,
+    Search,
+    SearchNext,
+    SearchPrev,
+    Filter,
+    Browser,
+    EditCard,
+    EditCardExternally,
+    AddCard,
     MarkToRepeat,
+    GradeAgain,
+    GradeHard,
+    GradeGood,
+    GradeEasy,
     TogglePronunciationVisibility,
     ToggleDescriptionVisibility,
     ToggleExampleVisibility,
@@ -35,6 +72,232 @@ pub enum Shortcut {
     ToggleZenMode,
     Help,
 }
+
+impl Shortcut {
+    // Name used in the config file
+    pub fn name(&self) -> &'static str {
+        match self {
+            Shortcut::Quit => "quit",
+            Shortcut::ReverseCard => "reverse_card",
+            Shortcut::PrevCard => "prev_card",
+            Shortcut::NextCard => "next_card",
+            Shortcut::FirstCard => "first_card",
+            Shortcut::LastCard => "last_card",
+            Shortcut::PageBack => "page_back",
+            Shortcut::PageForward => "page_forward",
+            Shortcut::GoToCard => "go_to_card",
+            Shortcut::Search => "search",
+            Shortcut::SearchNext => "search_next",
+            Shortcut::SearchPrev => "search_prev",
+            Shortcut::Filter => "filter",
+            Shortcut::Browser => "browser",
+            Shortcut::EditCard => "edit_card",
+            Shortcut::EditCardExternally => "edit_card_externally",
+            Shortcut::AddCard => "add_card",
+            Shortcut::MarkToRepeat => "mark_to_repeat",
+            Shortcut::GradeAgain => "grade_again",
+            Shortcut::GradeHard => "grade_hard",
+            Shortcut::GradeGood => "grade_good",
+            Shortcut::GradeEasy => "grade_easy",
+            Shortcut::TogglePronunciationVisibility => "toggle_pronunciation",
+            Shortcut::ToggleDescriptionVisibility => "toggle_description",
+            Shortcut::ToggleExampleVisibility => "toggle_example",
+            Shortcut::ToggleTitleVisibility => "toggle_title",
+            Shortcut::ToggleHintsVisibility => "toggle_hints",
+            Shortcut::ToggleZenMode => "toggle_zen_mode",
+            Shortcut::Help => "help",
+        }
+    }
+}
+
+impl FromStr for Shortcut {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ALL_SHORTCUTS
+            .iter()
+            .find(|shortcut| shortcut.name() == s)
+            .copied()
+            .ok_or_else(|| {
+                let names: Vec<&str> = ALL_SHORTCUTS.iter().map(Shortcut::name).collect();
+                format!(
+                    "unknown shortcut '{}', expected one of: {}",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Keymap {
+    bindings: HashMap<Shortcut, Vec<Event>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self {
+            bindings: ALL_SHORTCUTS
+                .iter()
+                .map(|shortcut| (*shortcut, shortcut.event()))
+                .collect(),
+        }
+    }
+}
+
+impl Keymap {
+    // Keys of shortcuts missing from `bindings` stay the default ones
+    pub fn with_bindings<'a, I>(bindings: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = (&'a str, &'a [String])>,
+    {
+        let mut keymap = Self::default();
+        for (name, keys) in bindings {
+            let shortcut: Shortcut = name.parse()?;
+            let events = keys
+                .iter()
+                .map(|key| parse_key(key).map_err(|e| format!("{} for {}", e, name)))
+                .collect::<Result<Vec<Event>, String>>()?;
+            keymap.bindings.insert(shortcut, events);
+        }
+        keymap.check_conflicts()?;
+        Ok(keymap)
+    }
+
+    pub fn shortcut(&self, event: &Event) -> Option<Shortcut> {
+        ALL_SHORTCUTS
+            .iter()
+            .copied()
+            .find(|shortcut| self.events(*shortcut).contains(event))
+    }
+
+    fn check_conflicts(&self) -> Result<(), String> {
+        let mut bound: HashMap<&Event, Shortcut> = HashMap::new();
+        let mut conflicts = vec![];
+        ALL_SHORTCUTS.iter().for_each(|shortcut| {
+            self.events(*shortcut)
+                .iter()
+                .for_each(|event| match bound.insert(event, *shortcut) {
+                    Some(other) if other != *shortcut => conflicts.push(format!(
+                        "'{}' is bound to {} and {}",
+                        event.help_text(),
+                        other.name(),
+                        shortcut.name()
+                    )),
+                    _ => {}
+                })
+        });
+        if conflicts.is_empty() {
+            Ok(())
+        } else {
+            Err(format!(
+                "conflicting key bindings: {}",
+                conflicts.join("; ")
+            ))
+        }
+    }
+
+    pub fn events(&self, shortcut: Shortcut) -> &[Event] {
+        self.bindings
+            .get(&shortcut)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    // The first key of the shortcut, as shown in the hints bar
+    pub fn hint(&self, shortcut: Shortcut) -> Option<String> {
+        self.events(shortcut).first().map(|event| event.help_text())
+    }
+}
+
+const KEY_NAMES: &[(Key, &str)] = &[
+    (Key::Enter, "enter"),
+    (Key::Tab, "tab"),
+    (Key::Backspace, "backspace"),
+    (Key::Esc, "esc"),
+    (Key::Left, "left"),
+    (Key::Right, "right"),
+    (Key::Up, "up"),
+    (Key::Down, "down"),
+    (Key::Ins, "insert"),
+    (Key::Del, "delete"),
+    (Key::Home, "home"),
+    (Key::End, "end"),
+    (Key::PageUp, "pageup"),
+    (Key::PageDown, "pagedown"),
+    (Key::PauseBreak, "pause"),
+    (Key::NumpadCenter, "numpad5"),
+    (Key::F1, "f1"),
+    (Key::F2, "f2"),
+    (Key::F3, "f3"),
+    (Key::F4, "f4"),
+    (Key::F5, "f5"),
+    (Key::F6, "f6"),
+    (Key::F7, "f7"),
+    (Key::F8, "f8"),
+    (Key::F9, "f9"),
+    (Key::F10, "f10"),
+    (Key::F11, "f11"),
+    (Key::F12, "f12"),
+];
+const MODIFIERS: [&str; 3] = ["ctrl+", "alt+", "shift+"];
+
+// Keys are single characters or key names, optionally prefixed with
+// modifiers, e.g. "G", "space", "pagedown", "ctrl+f", "alt+shift+left"
+pub fn parse_key(key: &str) -> Result<Event, String> {
+    let invalid = || format!("invalid key '{}'", key);
+    let mut modifiers = [false; 3];
+    let mut name = key;
+    while let Some(nr) = MODIFIERS.iter().position(|modifier| {
+        name.len() > modifier.len()
+            && name
+                .get(..modifier.len())
+                .is_some_and(|prefix| prefix.eq_ignore_ascii_case(modifier))
+    }) {
+        modifiers[nr] = true;
+        name = &name[MODIFIERS[nr].len()..];
+    }
+
+    let mut chars = name.chars();
+    let char = match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ if name.eq_ignore_ascii_case("space") => Some(' '),
+        _ => None,
+    };
+    let event = match (char, modifiers) {
+        (Some(c), [false, false, false]) => Event::Char(c),
+        (Some(c), [true, false, false]) => Event::CtrlChar(c.to_ascii_lowercase()),
+        (Some(c), [false, true, false]) => Event::AltChar(c),
+        (Some(_), _) => return Err(invalid()),
+        (None, modifiers) => {
+            let key = KEY_NAMES
+                .iter()
+                .find(|(_, key_name)| name.eq_ignore_ascii_case(key_name))
+                .map(|(key, _)| *key)
+                .ok_or_else(invalid)?;
+            match modifiers {
+                [false, false, false] => Event::Key(key),
+                [false, false, true] => Event::Shift(key),
+                [false, true, false] => Event::Alt(key),
+                [false, true, true] => Event::AltShift(key),
+                [true, false, false] => Event::Ctrl(key),
+                [true, false, true] => Event::CtrlShift(key),
+                [true, true, false] => Event::CtrlAlt(key),
+                [true, true, true] => return Err(invalid()),
+            }
+        }
+    };
+    Ok(event)
+}
+
+fn key_name(key: &Key) -> String {
+    KEY_NAMES
+        .iter()
+        .find(|(named, _)| named == key)
+        .map(|(_, name)| name.to_string())
+        .unwrap_or_else(|| format!("{:?}", key).to_lowercase())
+}
+
 pub trait DisplayEventInHelp {
     fn help_text(&self) -> String;
 }
@@ -46,7 +309,14 @@ impl DisplayEventInHelp for Event {
             Event::Char(c) => format!("{}", c),
             Event::AltChar(c) => format!("alt+{}", c),
             Event::CtrlChar(c) => format!("ctrl+{}", c),
-            e => panic!("Cannot format {:?} for display in help", e),
+            Event::Key(key) => key_name(key),
+            Event::Shift(key) => format!("shift+{}", key_name(key)),
+            Event::Alt(key) => format!("alt+{}", key_name(key)),
+            Event::AltShift(key) => format!("alt+shift+{}", key_name(key)),
+            Event::Ctrl(key) => format!("ctrl+{}", key_name(key)),
+            Event::CtrlShift(key) => format!("ctrl+shift+{}", key_name(key)),
+            Event::CtrlAlt(key) => format!("ctrl+alt+{}", key_name(key)),
+            e => format!("{:?}", e),
         }
     }
 }
@@ -62,9 +332,30 @@ impl ShortcutData for Shortcut {
         match self {
             Shortcut::Quit => vec![Event::Char('q')],
             Shortcut::ReverseCard => vec![Event::Char(' ')],
-            Shortcut::PrevCard => vec![Event::Char('p'), Event::Char('h')],
-            Shortcut::NextCard => vec![Event::Char('n'), Event::Char('l')],
+            Shortcut::PrevCard => vec![Event::Char('p'), Event::Char('h'), Event::Key(Key::Left)],
+            Shortcut::NextCard => vec![
+                Event::Char('l'),
+                Event::Key(Key::Right),
+                Event::Key(Key::Enter),
+            ],
+            Shortcut::FirstCard => vec![Event::Char('g'), Event::Key(Key::Home)],
+            Shortcut::LastCard => vec![Event::Char('G'), Event::Key(Key::End)],
+            Shortcut::PageBack => vec![Event::Key(Key::PageUp)],
+            Shortcut::PageForward => vec![Event::Key(Key::PageDown)],
+            Shortcut::GoToCard => vec![Event::Char(':')],
+            Shortcut::Search => vec![Event::Char('/')],
+            Shortcut::SearchNext => vec![Event::Char('n')],
+            Shortcut::SearchPrev => vec![Event::Char('N')],
+            Shortcut::Filter => vec![Event::Char('f')],
+            Shortcut::Browser => vec![Event::Char('B')],
+            Shortcut::EditCard => vec![Event::Char('E')],
+            Shortcut::EditCardExternally => vec![Event::Char('V')],
+            Shortcut::AddCard => vec![Event::Char('A')],
             Shortcut::MarkToRepeat => vec![Event::Char('r')],
+            Shortcut::GradeAgain => vec![Event::Char('y')],
+            Shortcut::GradeHard => vec![Event::Char('u')],
+            Shortcut::GradeGood => vec![Event::Char('i')],
+            Shortcut::GradeEasy => vec![Event::Char('o')],
             Shortcut::TogglePronunciationVisibility => vec![Event::Char('a')],
             Shortcut::ToggleDescriptionVisibility => vec![Event::Char('d')],
             Shortcut::ToggleExampleVisibility => vec![Event::Char('e')],
@@ -81,7 +372,24 @@ impl ShortcutData for Shortcut {
             Shortcut::ReverseCard => "Reverse card",
             Shortcut::PrevCard => "Previous card",
             Shortcut::NextCard => "Next card",
+            Shortcut::FirstCard => "First card / with count N: card N",
+            Shortcut::LastCard => "Last card / with count N: card N",
+            Shortcut::PageBack => "10 cards back",
+            Shortcut::PageForward => "10 cards forward",
+            Shortcut::GoToCard => "Go to card with given number",
+            Shortcut::Search => "Search cards",
+            Shortcut::SearchNext => "Next search match / without search: next card",
+            Shortcut::SearchPrev => "Previous search match / without search: previous card",
+            Shortcut::Filter => "Filter cards by tag, type or deck",
+            Shortcut::Browser => "Browse all cards",
+            Shortcut::EditCard => "Edit current card",
+            Shortcut::EditCardExternally => "Edit current card in $EDITOR",
+            Shortcut::AddCard => "Add new card",
             Shortcut::MarkToRepeat => "Mark card to be repeated in the next round",
+            Shortcut::GradeAgain => "Reversed card: forgot it, repeat it",
+            Shortcut::GradeHard => "Reversed card: recalled with difficulty",
+            Shortcut::GradeGood => "Reversed card: recalled",
+            Shortcut::GradeEasy => "Reversed card: recalled easily",
             Shortcut::TogglePronunciationVisibility => "Show/hide pronunciation",
             Shortcut::ToggleDescriptionVisibility => "Show/hide term description",
             Shortcut::ToggleExampleVisibility => "Show/hide example sentence",
@@ -94,12 +402,30 @@ impl ShortcutData for Shortcut {
     }
 
     fn call(&self, siv: &mut Cursive) {
+        let count = card_logic::take_count(siv);
         match self {
             Shortcut::Quit => back_or_quit(siv),
             Shortcut::ReverseCard => card_logic::reverse_card(siv),
-            Shortcut::PrevCard => card_logic::prev_card(siv),
-            Shortcut::NextCard => card_logic::next_card(siv),
+            Shortcut::PrevCard => card_logic::prev_card(siv, count),
+            Shortcut::NextCard => card_logic::next_card(siv, count),
+            Shortcut::FirstCard => card_logic::first_card(siv, count),
+            Shortcut::LastCard => card_logic::last_card(siv, count),
+            Shortcut::PageBack => card_logic::page_back(siv, count),
+            Shortcut::PageForward => card_logic::page_forward(siv, count),
+            Shortcut::GoToCard => card_logic::go_to_card(siv),
+            Shortcut::Search => card_logic::search(siv),
+            Shortcut::SearchNext => card_logic::next_match(siv, true, count),
+            Shortcut::SearchPrev => card_logic::next_match(siv, false, count),
+            Shortcut::Filter => card_logic::filter(siv),
+            Shortcut::Browser => card_logic::show_browser(siv),
+            Shortcut::EditCard => card_logic::edit_card(siv),
+            Shortcut::EditCardExternally => card_logic::edit_card_externally(siv),
+            Shortcut::AddCard => card_logic::new_card(siv),
             Shortcut::MarkToRepeat => card_logic::toggle_card_needs_repetition(siv),
+            Shortcut::GradeAgain => card_logic::grade_card(siv, Grade::Again),
+            Shortcut::GradeHard => card_logic::grade_card(siv, Grade::Hard),
+            Shortcut::GradeGood => card_logic::grade_card(siv, Grade::Good),
+            Shortcut::GradeEasy => card_logic::grade_card(siv, Grade::Easy),
             Shortcut::TogglePronunciationVisibility => card_logic::toggle_pronunciation(siv),
             Shortcut::ToggleDescriptionVisibility => card_logic::toggle_description(siv),
             Shortcut::ToggleExampleVisibility => card_logic::toggle_example(siv),
@@ -111,11 +437,219 @@ impl ShortcutData for Shortcut {
     }
 }
 
+pub fn call_or_count(siv: &mut Cursive, digit: char, shortcut: Option<Shortcut>) {
+    let counting = card_logic::pending_count(siv).is_some();
+    match digit_action(digit, shortcut, counting, card_logic::awaits_choice(siv)) {
+        DigitAction::Call(shortcut) => shortcut.call(siv),
+        DigitAction::Choose(nr) => card_logic::choose(siv, nr),
+        DigitAction::Count(digit) => card_logic::add_to_count(siv, digit),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum DigitAction {
+    Call(Shortcut),
+    Choose(usize),
+    Count(usize),
+}
+
+// A started count takes every digit. Otherwise 1-4 pick the numbered answer of a
+// choice card, a digit bound in the config runs its shortcut and the rest start a count
+fn digit_action(
+    digit: char,
+    shortcut: Option<Shortcut>,
+    counting: bool,
+    awaits_choice: bool,
+) -> DigitAction {
+    let value = digit.to_digit(10).unwrap_or(0) as usize;
+    match shortcut {
+        _ if counting => DigitAction::Count(value),
+        _ if awaits_choice && (1..=CHOICES).contains(&value) => DigitAction::Choose(value - 1),
+        Some(shortcut) => DigitAction::Call(shortcut),
+        None => DigitAction::Count(value),
+    }
+}
+
 fn back_or_quit(siv: &mut Cursive) {
-    match siv.find_name::<Dialog>(help::HELP_DIALOG) {
-        None => siv.quit(),
-        Some(_) => {
-            siv.pop_layer();
-        }
-    };
+    if card_logic::dialog_shown(siv) && !card_logic::summary_shown(siv) {
+        siv.pop_layer();
+    } else {
+        card_logic::quit(siv);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(keys: &[&str]) -> Vec<String> {
+        keys.iter().map(|key| key.to_string()).collect()
+    }
+
+    #[test]
+    fn count_prefix_works_on_a_reversed_card() {
+        let keymap = Keymap::default();
+        let shortcut = |key| keymap.shortcut(&Event::Char(key));
+
+        // "25l"
+        assert_eq!(
+            DigitAction::Count(2),
+            digit_action('2', shortcut('2'), false, false)
+        );
+        assert_eq!(
+            DigitAction::Count(5),
+            digit_action('5', shortcut('5'), true, false)
+        );
+        assert_eq!(Some(Shortcut::NextCard), shortcut('l'));
+    }
+
+    #[test]
+    fn digits_pick_answers_of_choice_cards() {
+        assert_eq!(DigitAction::Choose(0), digit_action('1', None, false, true));
+        assert_eq!(DigitAction::Choose(3), digit_action('4', None, false, true));
+        assert_eq!(DigitAction::Count(5), digit_action('5', None, false, true));
+        assert_eq!(DigitAction::Count(0), digit_action('0', None, false, true));
+        assert_eq!(DigitAction::Count(2), digit_action('2', None, true, true));
+    }
+
+    #[test]
+    fn digits_bound_in_config_run_their_shortcut() {
+        let help = Some(Shortcut::Help);
+
+        assert_eq!(
+            DigitAction::Call(Shortcut::Help),
+            digit_action('7', help, false, false)
+        );
+        assert_eq!(DigitAction::Count(7), digit_action('7', help, true, false));
+    }
+
+    #[test]
+    fn default_keymap_uses_default_events() {
+        let keymap = Keymap::default();
+
+        assert_eq!(
+            &[Event::Char('g'), Event::Key(Key::Home)],
+            keymap.events(Shortcut::FirstCard)
+        );
+        assert_eq!(Some("q".to_string()), keymap.hint(Shortcut::Quit));
+    }
+
+    #[test]
+    fn bindings_replace_default_keys() {
+        let next = keys(&["j", "ctrl+n"]);
+        let help = keys(&[]);
+
+        let keymap = Keymap::with_bindings(vec![
+            ("next_card", next.as_slice()),
+            ("help", help.as_slice()),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            &[Event::Char('j'), Event::CtrlChar('n')],
+            keymap.events(Shortcut::NextCard)
+        );
+        assert_eq!(
+            &[Event::Char('p'), Event::Char('h'), Event::Key(Key::Left)],
+            keymap.events(Shortcut::PrevCard)
+        );
+        assert_eq!(None, keymap.hint(Shortcut::Help));
+    }
+
+    #[test]
+    fn unknown_shortcut_is_reported() {
+        let next = keys(&["j"]);
+
+        let error = Keymap::with_bindings(vec![("next", next.as_slice())]).unwrap_err();
+
+        assert!(error.starts_with("unknown shortcut 'next', expected one of: quit, reverse_card"));
+    }
+
+    #[test]
+    fn invalid_key_is_reported() {
+        let next = keys(&["jj"]);
+
+        let error = Keymap::with_bindings(vec![("next_card", next.as_slice())]).unwrap_err();
+
+        assert_eq!("invalid key 'jj' for next_card", error);
+    }
+
+    #[test]
+    fn named_keys_and_modifiers() {
+        assert_eq!(Ok(Event::Char('G')), parse_key("G"));
+        assert_eq!(Ok(Event::Char('+')), parse_key("+"));
+        assert_eq!(Ok(Event::Char(' ')), parse_key("Space"));
+        assert_eq!(Ok(Event::Key(Key::PageDown)), parse_key("PageDown"));
+        assert_eq!(Ok(Event::Key(Key::F5)), parse_key("f5"));
+        assert_eq!(Ok(Event::CtrlChar('f')), parse_key("ctrl+F"));
+        assert_eq!(Ok(Event::AltChar('x')), parse_key("alt+x"));
+        assert_eq!(Ok(Event::Shift(Key::Tab)), parse_key("shift+tab"));
+        assert_eq!(Ok(Event::CtrlAlt(Key::Del)), parse_key("alt+ctrl+delete"));
+        assert_eq!(Ok(Event::CtrlChar('+')), parse_key("ctrl++"));
+    }
+
+    #[test]
+    fn invalid_keys() {
+        assert!(parse_key("").is_err());
+        assert!(parse_key("f13").is_err());
+        assert!(parse_key("shift+a").is_err());
+        assert!(parse_key("ctrl+alt+shift+up").is_err());
+    }
+
+    #[test]
+    fn every_key_is_shown_in_help_as_it_is_parsed() {
+        let keys = KEY_NAMES.iter().map(|(_, name)| name.to_string()).chain(
+            [
+                "space",
+                "x",
+                "ctrl+x",
+                "alt+x",
+                "shift+up",
+                "alt+shift+up",
+                "ctrl+shift+up",
+            ]
+            .iter()
+            .map(|key| key.to_string()),
+        );
+
+        keys.for_each(|key| assert_eq!(key, parse_key(&key).unwrap().help_text()));
+    }
+
+    #[test]
+    fn events_without_name_are_shown_in_help() {
+        assert_eq!("WindowResize", Event::WindowResize.help_text());
+        assert_eq!("f0", Event::Key(Key::F0).help_text());
+    }
+
+    #[test]
+    fn conflicting_bindings_are_reported() {
+        let quit = keys(&["n"]);
+        let help = keys(&["home"]);
+
+        let error =
+            Keymap::with_bindings(vec![("quit", quit.as_slice()), ("help", help.as_slice())])
+                .unwrap_err();
+
+        assert_eq!(
+            "conflicting key bindings: 'n' is bound to quit and search_next; 'home' is bound to first_card and help",
+            error
+        );
+    }
+
+    #[test]
+    fn default_bindings_do_not_conflict() {
+        assert_eq!(Ok(()), Keymap::default().check_conflicts());
+    }
+
+    #[test]
+    fn shortcut_is_found_by_event() {
+        let keymap = Keymap::default();
+
+        assert_eq!(
+            Some(Shortcut::GradeHard),
+            keymap.shortcut(&Event::Char('u'))
+        );
+        assert_eq!(Some(Shortcut::LastCard), keymap.shortcut(&Event::Char('G')));
+        assert_eq!(None, keymap.shortcut(&Event::Char('2')));
+    }
 }
@@ -0,0 +1,179 @@
+use std::fs;
+use std::time::Duration;
+
+use chrono::Local;
+
+use crate::card::card_data::{CardSet, Grade};
+use crate::error::Error;
+
+// What was done in a session; shown before quitting and written as JSON for tracking
+#[derive(serde::Serialize, Debug, PartialEq)]
+pub struct Summary {
+    pub deck: String,
+    pub finished_at: String,
+    pub cards_seen: usize,
+    pub cards_reversed: usize,
+    pub cards_marked: usize,
+    pub seconds_spent: u64,
+    pub average_seconds_per_card: f64,
+    pub answers: Option<Answers>,
+}
+
+#[derive(serde::Serialize, Debug, PartialEq)]
+pub struct Answers {
+    pub again: usize,
+    pub hard: usize,
+    pub good: usize,
+    pub easy: usize,
+}
+
+impl Answers {
+    // None when no card was graded
+    pub fn of(card_set: &CardSet) -> Option<Self> {
+        if card_set.answers().is_empty() {
+            return None;
+        }
+        let count = |grade: Grade| {
+            card_set
+                .answers()
+                .iter()
+                .filter(|(_, answer)| *answer == grade)
+                .count()
+        };
+        Some(Self {
+            again: count(Grade::Again),
+            hard: count(Grade::Hard),
+            good: count(Grade::Good),
+            easy: count(Grade::Easy),
+        })
+    }
+
+    pub fn text(&self) -> String {
+        format!(
+            "again {}, hard {}, good {}, easy {}",
+            self.again, self.hard, self.good, self.easy
+        )
+    }
+}
+
+impl Summary {
+    pub fn of(card_set: &CardSet, spent: Duration) -> Self {
+        let cards = card_set.cards();
+        let cards_seen = cards.iter().filter(|card| card.seen).count();
+        let seconds_spent = spent.as_secs();
+        let average_seconds_per_card = if cards_seen > 0 {
+            (seconds_spent as f64 / cards_seen as f64 * 10.0).round() / 10.0
+        } else {
+            0.0
+        };
+        Self {
+            deck: card_set.name.clone(),
+            finished_at: Local::now().to_rfc3339(),
+            cards_seen,
+            cards_reversed: cards.iter().filter(|card| card.revealed).count(),
+            cards_marked: card_set.marked_cards().count(),
+            seconds_spent,
+            average_seconds_per_card,
+            answers: Answers::of(card_set),
+        }
+    }
+
+    pub fn text(&self) -> String {
+        let mut text = format!(
+            "Cards seen: {}\nCards reversed: {}\nMarked to be repeated: {}\nTime spent: {}\nAverage per card: {:.1}s",
+            self.cards_seen,
+            self.cards_reversed,
+            self.cards_marked,
+            duration_text(self.seconds_spent),
+            self.average_seconds_per_card
+        );
+        if let Some(answers) = &self.answers {
+            text.push_str(&format!("\nAnswers: {}", answers.text()));
+        }
+        text
+    }
+
+    // "-" writes to stdout
+    pub fn write_json(&self, target: &str) -> Result<(), Error> {
+        let json = serde_json::to_string_pretty(self)?;
+        if target == "-" {
+            println!("{}", json);
+        } else {
+            fs::write(target, json + "\n")
+                .map_err(|e| format!("Cannot write summary to {}: {}", target, e))?;
+        }
+        Ok(())
+    }
+}
+
+fn duration_text(seconds: u64) -> String {
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}h {:02}m {:02}s", hours, minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}m {:02}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::card_data::CardData;
+
+    fn card_set() -> CardSet {
+        let cards = ["kot", "pies", "mysz"]
+            .iter()
+            .map(|word| CardData {
+                word: word.to_string(),
+                translated: word.to_uppercase(),
+                ..CardData::default()
+            })
+            .collect();
+        CardSet::new("animals", cards)
+    }
+
+    #[test]
+    fn session_is_summarized() {
+        let mut card_set = card_set();
+        card_set.reverse_current_card();
+        card_set.grade_current_card(Grade::Again);
+        card_set.reverse_current_card();
+        card_set.grade_current_card(Grade::Easy);
+
+        let summary = Summary::of(&card_set, Duration::from_secs(100));
+
+        assert_eq!(3, summary.cards_seen);
+        assert_eq!(2, summary.cards_reversed);
+        assert_eq!(1, summary.cards_marked);
+        assert_eq!(33.3, summary.average_seconds_per_card);
+        assert_eq!(
+            Some(Answers {
+                again: 1,
+                hard: 0,
+                good: 0,
+                easy: 1
+            }),
+            summary.answers
+        );
+    }
+
+    #[test]
+    fn answers_are_left_out_without_grades() {
+        let summary = Summary::of(&card_set(), Duration::from_secs(75));
+
+        assert_eq!(None, summary.answers);
+        assert_eq!(
+            "Cards seen: 1\nCards reversed: 0\nMarked to be repeated: 0\nTime spent: 1m 15s\nAverage per card: 75.0s",
+            summary.text()
+        );
+    }
+
+    #[test]
+    fn durations_are_readable() {
+        assert_eq!("0s", duration_text(0));
+        assert_eq!("2m 05s", duration_text(125));
+        assert_eq!("1h 00m 07s", duration_text(3607));
+    }
+}
@@ -0,0 +1,21 @@
+use std::env;
+use std::path::{Path, PathBuf};
+
+const APP_DIR: &str = "worm";
+
+pub fn config_dir() -> Option<PathBuf> {
+    app_dir("XDG_CONFIG_HOME", ".config")
+}
+
+pub fn state_dir() -> Option<PathBuf> {
+    app_dir("XDG_STATE_HOME", ".local/state")
+}
+
+// Relative paths in XDG variables are invalid and should be ignored
+fn app_dir(variable: &str, default_in_home: &str) -> Option<PathBuf> {
+    env::var_os(variable)
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(default_in_home)))
+        .map(|dir| dir.join(APP_DIR))
+}